@@ -0,0 +1,45 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::env;
+
+/*
+ * libcontract(3LIB) only exists on illumos and Solaris.  When building for
+ * those systems, link against it and set the "libcontract" cfg, which the
+ * crate uses to choose between the real library and the stand-ins in the
+ * "stub" module.  Elsewhere, nothing is linked, so that the crate can still
+ * be built for documentation and for checking when cross-compiling.
+ *
+ * The library name and search path can be overridden with the
+ * LIBCONTRACT_LIB_NAME and LIBCONTRACT_LIB_DIR environment variables, e.g.,
+ * to link against the library in a sysroot for another release.
+ *
+ * Because this package sets "links = contract", the build scripts of crates
+ * that depend on it directly see DEP_CONTRACT_SUPPORTED set to "1" when the
+ * library is available, and to "0" when it is not.
+ */
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=LIBCONTRACT_LIB_NAME");
+    println!("cargo:rerun-if-env-changed=LIBCONTRACT_LIB_DIR");
+    println!("cargo:rustc-check-cfg=cfg(libcontract)");
+
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if os != "illumos" && os != "solaris" {
+        println!("cargo:supported=0");
+        return;
+    }
+
+    if let Some(dir) = env::var_os("LIBCONTRACT_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={}", dir.to_string_lossy());
+    }
+    let name = env::var("LIBCONTRACT_LIB_NAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "contract".to_string());
+    println!("cargo:rustc-link-lib=dylib={}", name);
+
+    println!("cargo:rustc-cfg=libcontract");
+    println!("cargo:supported=1");
+}
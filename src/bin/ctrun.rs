@@ -0,0 +1,170 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+/*
+ * A ctrun(1)-like tool: runs a command in a new process contract, relays
+ * SIGHUP, SIGINT, SIGQUIT and SIGTERM to every member of the contract, and
+ * waits until the contract is empty.
+ *
+ *   ctrun [-f event,...] command [argument ...]
+ *
+ *   -f  the fatal events, e.g., "core,signal,hwerr"; by default, the default
+ *       fatal set of process(5)
+ *
+ * The exit status is that of the command, or 128 plus the number of the
+ * signal that killed it.
+ */
+
+use std::mem::MaybeUninit;
+use std::os::raw::c_int;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{exit, Command};
+use std::ptr;
+use std::thread;
+
+use libcontract_sys::ids::ContractId;
+use libcontract_sys::safe::{
+    run_in_contract, Contract, PrEventSet, ProcessTemplateBuilder,
+};
+use libcontract_sys::CT_PR_EV_EMPTY;
+
+const USAGE: &str = "usage: ctrun [-f event,...] command [argument ...]";
+
+const RELAYED: [c_int; 4] =
+    [libc::SIGHUP, libc::SIGINT, libc::SIGQUIT, libc::SIGTERM];
+
+#[cfg(libcontract)]
+extern "C" {
+    /*
+     * See sigsend(2); this is not provided by the libc crate.
+     */
+    fn sigsend(idtype: libc::idtype_t, id: libc::id_t, sig: c_int) -> c_int;
+}
+
+/*
+ * Sends a signal to every member of a process contract.
+ */
+#[cfg(libcontract)]
+fn signal_contract(ctid: ContractId, sig: c_int) {
+    unsafe { sigsend(libc::P_CTID, ctid.as_raw() as libc::id_t, sig) };
+}
+
+/*
+ * Without contracts, no contract can have been created to signal.
+ */
+#[cfg(not(libcontract))]
+fn signal_contract(_: ContractId, _: c_int) {}
+
+fn main() {
+    let (config, command) = parse_args().unwrap_or_else(|msg| {
+        eprintln!("ctrun: {}\n{}", msg, USAGE);
+        exit(2);
+    });
+
+    match run(command, &config) {
+        Ok(code) => exit(code),
+        Err(msg) => {
+            eprintln!("ctrun: {}", msg);
+            exit(1);
+        }
+    }
+}
+
+fn parse_args() -> Result<(ProcessTemplateBuilder, Command), String> {
+    let mut args = std::env::args().skip(1).peekable();
+    let mut config = ProcessTemplateBuilder::new()
+        .critical(PrEventSet::EMPTY | PrEventSet::HWERR);
+
+    if args.peek().map(String::as_str) == Some("-f") {
+        args.next();
+        let events = args.next().ok_or("-f requires an argument")?;
+        config = config.fatal(parse_events(&events)?);
+    }
+
+    let prog = args.next().ok_or("no command specified")?;
+    let mut command = Command::new(prog);
+    command.args(args);
+
+    Ok((config, command))
+}
+
+fn parse_events(s: &str) -> Result<PrEventSet, String> {
+    s.split(',').try_fold(PrEventSet::empty(), |set, name| {
+        let ev = match name {
+            "core" => PrEventSet::CORE,
+            "signal" => PrEventSet::SIGNAL,
+            "hwerr" => PrEventSet::HWERR,
+            "exit" => PrEventSet::EXIT,
+            "fork" => PrEventSet::FORK,
+            "empty" => PrEventSet::EMPTY,
+            _ => return Err(format!("unknown event: {}", name)),
+        };
+        Ok(set | ev)
+    })
+}
+
+fn run(
+    command: Command,
+    config: &ProcessTemplateBuilder,
+) -> Result<i32, String> {
+    /*
+     * The relayed signals are blocked before any threads are created, so
+     * that they are only ever received by the relay thread's sigwait().  The
+     * child's signal mask is reset when it is spawned.
+     */
+    let set = relayed_set();
+    if unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) }
+        != 0
+    {
+        return Err("could not block signals".into());
+    }
+
+    let (mut child, ct) =
+        run_in_contract(command, config).map_err(|e| e.to_string())?;
+    let ctid = ct.id();
+    thread::spawn(move || relay(set, ctid));
+
+    wait_empty(&ct).map_err(|e| e.to_string())?;
+    let status = child.wait().map_err(|e| e.to_string())?;
+    let _ = ct.abandon();
+
+    Ok(match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(sig)) => 128 + sig,
+        (None, None) => 1,
+    })
+}
+
+fn relayed_set() -> libc::sigset_t {
+    let mut set = MaybeUninit::uninit();
+    unsafe {
+        libc::sigemptyset(set.as_mut_ptr());
+        for sig in RELAYED {
+            libc::sigaddset(set.as_mut_ptr(), sig);
+        }
+        set.assume_init()
+    }
+}
+
+fn relay(set: libc::sigset_t, ctid: ContractId) {
+    loop {
+        let mut sig = 0;
+        if unsafe { libc::sigwait(&set, &mut sig) } != 0 {
+            continue;
+        }
+        signal_contract(ctid, sig);
+    }
+}
+
+fn wait_empty(ct: &Contract) -> Result<(), std::io::Error> {
+    for ev in ct.events()? {
+        let ev = ev?;
+        let _ = ev.ack(ct);
+        if ev.event_type() == CT_PR_EV_EMPTY {
+            break;
+        }
+    }
+
+    Ok(())
+}
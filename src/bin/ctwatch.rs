@@ -0,0 +1,127 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+/*
+ * A ctwatch(1)-like tool: watches the events of contracts, or of every
+ * contract of a type, and prints them as they arrive.
+ *
+ *   ctwatch [-a] [-j] [-r] ctid|type ...
+ *
+ *   -a  acknowledge critical events of contracts that we hold
+ *   -j  print events as JSON, one per line
+ *   -r  request reliable delivery (requires {PRIV_CONTRACT_OBSERVER})
+ *
+ * A "type" operand, i.e., "process" or "device", watches the bundle for that
+ * contract type.  With -a, it instead watches the process bundle, which
+ * carries the events of the contracts of that type that we hold: events read
+ * from the bundle cannot be acknowledged.
+ */
+
+use std::process::exit;
+
+use libcontract_sys::ct_typeid_t;
+use libcontract_sys::safe::{
+    Contract, ContractError, Event, EventEndpoint, EventPortWatcher, PortEvent,
+};
+
+const USAGE: &str = "usage: ctwatch [-a] [-j] [-r] ctid|type ...";
+
+struct Options {
+    ack: bool,
+    json: bool,
+    reliable: bool,
+    operands: Vec<String>,
+}
+
+fn main() {
+    let opts = parse_args().unwrap_or_else(|msg| {
+        eprintln!("ctwatch: {}\n{}", msg, USAGE);
+        exit(2);
+    });
+
+    if let Err(e) = run(&opts) {
+        eprintln!("ctwatch: {}", e);
+        exit(1);
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut opts =
+        Options { ack: false, json: false, reliable: false, operands: vec![] };
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "-a" => opts.ack = true,
+            "-j" => opts.json = true,
+            "-r" => opts.reliable = true,
+            s if s.starts_with('-') => {
+                return Err(format!("unknown option: {}", s))
+            }
+            _ => opts.operands.push(arg),
+        }
+    }
+
+    if opts.operands.is_empty() {
+        return Err("no contracts specified".into());
+    }
+
+    Ok(opts)
+}
+
+fn open(opts: &Options, operand: &str) -> Result<EventEndpoint, String> {
+    let res = match ct_typeid_t::from_type_name(operand) {
+        Some(ty) if opts.ack => EventEndpoint::pbundle(ty),
+        Some(ty) => EventEndpoint::bundle(ty),
+        None => {
+            let ctid = operand
+                .parse()
+                .map_err(|_| format!("invalid contract: {}", operand))?;
+            EventEndpoint::for_contract(ctid)
+        }
+    };
+
+    res.map_err(|e| format!("{}: {}", operand, e))
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    let mut watcher = EventPortWatcher::new().map_err(|e| e.to_string())?;
+
+    for operand in &opts.operands {
+        let ep = open(opts, operand)?;
+        if opts.reliable {
+            ep.reliable().map_err(|e| format!("{}: {}", operand, e))?;
+        }
+        watcher.add(ep).map_err(|e| format!("{}: {}", operand, e))?;
+    }
+
+    while !watcher.is_empty() {
+        for pev in watcher.wait(None).map_err(|e| e.to_string())? {
+            match pev {
+                PortEvent::Contract { event, .. } => print(opts, &event),
+                PortEvent::Error { endpoint, error } => {
+                    eprintln!("ctwatch: read failed: {}", error);
+                    watcher.remove(endpoint);
+                }
+                PortEvent::Fd { .. } => (),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print(opts: &Options, ev: &Event) {
+    if opts.json {
+        println!("{}", ev.to_json());
+    } else {
+        println!("{}", ev);
+    }
+
+    if opts.ack {
+        match Contract::open(ev.ctid()).and_then(|ct| ev.ack(&ct)) {
+            Ok(()) | Err(ContractError::ResponseNotAllowed) => (),
+            Err(e) => eprintln!("ctwatch: ack of {} failed: {}", ev.evid(), e),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+//! Earlier signatures of the process and device contract status functions,
+//! which took their status handle as a `*const` pointer.  All handles are now
+//! passed as `*mut` pointers, consistent with the rest of the bindings; these
+//! wrappers exist only to ease migration and will be removed in a future
+//! release.
+//!
+//! There are no equivalents for the earlier ct_pr_event_get_*(3CONTRACT)
+//! signatures, which took the opaque ct_evthdl_t by value: those could not
+//! pass the handle to the library at all.
+
+#![allow(deprecated)]
+
+use std::os::raw::{c_char, c_int, c_uint};
+
+use libc::pid_t;
+
+use crate as sys;
+use crate::{ct_stathdl_t, ctid_t};
+
+macro_rules! const_handle_compat {
+    ($(fn $name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[deprecated(note = "status handles are now passed as *mut")]
+            #[doc = concat!("See [`crate::", stringify!($name), "`].")]
+            ///
+            /// # Safety
+            ///
+            /// As for the underlying libcontract(3LIB) function.
+            pub unsafe fn $name(
+                stathdl: *const ct_stathdl_t,
+                $($arg: $ty),*
+            ) -> c_int {
+                sys::$name(stathdl as *mut ct_stathdl_t, $($arg),*)
+            }
+        )*
+    };
+}
+
+const_handle_compat! {
+    fn ct_pr_status_get_param(paramp: *mut c_uint);
+    fn ct_pr_status_get_fatal(eventsp: *mut c_uint);
+    fn ct_pr_status_get_members(pidpp: *mut *mut pid_t, n: *mut c_uint);
+    fn ct_pr_status_get_contracts(idpp: *mut *mut ctid_t, n: *mut c_uint);
+    fn ct_pr_status_get_svc_fmri(fmri: *mut *mut c_char);
+    fn ct_pr_status_get_svc_aux(aux: *mut *mut c_char);
+    fn ct_pr_status_get_svc_ctid(ctid: *mut ctid_t);
+    fn ct_pr_status_get_svc_creator(creator: *mut *mut c_char);
+    fn ct_dev_status_get_dev_state(statep: *mut c_uint);
+    fn ct_dev_status_get_aset(asetp: *mut c_uint);
+    fn ct_dev_status_get_minor(minorp: *mut *mut c_char);
+    fn ct_dev_status_get_noneg(nonegp: *mut c_uint);
+}
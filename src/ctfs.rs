@@ -0,0 +1,76 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+//! Paths within the contract file system; see ctfs(4FS).
+
+use std::path::PathBuf;
+
+use crate::ctid_t;
+
+pub const CTFS_ROOT: &str = "/system/contract";
+
+/*
+ * Every contract appears in the "all" directory, regardless of its type:
+ */
+pub const CTFS_ALL: &str = "/system/contract/all";
+
+/*
+ * Contract type names, as used for the per-type directories:
+ */
+pub const PROCESS_TYPE: &str = "process";
+pub const DEVICE_TYPE: &str = "device";
+
+pub const PROCESS_TEMPLATE: &str = "/system/contract/process/template";
+pub const DEVICE_TEMPLATE: &str = "/system/contract/device/template";
+
+/*
+ * Reading the "latest" file returns the status of the contract of that type
+ * most recently created by the calling LWP:
+ */
+pub const PROCESS_LATEST: &str = "/system/contract/process/latest";
+
+/// Returns the directory for a contract type, e.g., `/system/contract/process`.
+pub fn type_path(type_name: &str) -> PathBuf {
+    [CTFS_ROOT, type_name].iter().collect()
+}
+
+/// Returns the template endpoint for a contract type.
+pub fn template_path(type_name: &str) -> PathBuf {
+    type_path(type_name).join("template")
+}
+
+/// Returns the "latest" endpoint for a contract type.
+pub fn latest_path(type_name: &str) -> PathBuf {
+    type_path(type_name).join("latest")
+}
+
+/// Returns the bundle endpoint for a contract type, from which events for
+/// all contracts of that type can be read.
+pub fn bundle_path(type_name: &str) -> PathBuf {
+    type_path(type_name).join("bundle")
+}
+
+/// Returns the process bundle endpoint for a contract type, from which
+/// events for all contracts of that type held by the reading process can be
+/// read.
+pub fn pbundle_path(type_name: &str) -> PathBuf {
+    type_path(type_name).join("pbundle")
+}
+
+/// Returns the directory for a contract.
+pub fn contract_path(ctid: ctid_t) -> PathBuf {
+    PathBuf::from(CTFS_ALL).join(ctid.to_string())
+}
+
+pub fn ctl_path(ctid: ctid_t) -> PathBuf {
+    contract_path(ctid).join("ctl")
+}
+
+pub fn status_path(ctid: ctid_t) -> PathBuf {
+    contract_path(ctid).join("status")
+}
+
+pub fn events_path(ctid: ctid_t) -> PathBuf {
+    contract_path(ctid).join("events")
+}
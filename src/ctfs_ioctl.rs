@@ -0,0 +1,54 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+//! The ioctl(2) requests understood by the files in the contract file
+//! system, from sys/ctfs.h.  libcontract(3LIB) is implemented with these,
+//! and they are a Private interface that may change between releases; the
+//! library should be preferred wherever it is sufficient.
+
+use std::os::raw::c_int;
+
+/*
+ * Each request is made up of a magic prefix, the kind of file it applies to,
+ * and a number.
+ */
+const CTFS_PREFIX: c_int = (b'c' as c_int) << 24 | (b't' as c_int) << 16;
+
+const fn ctfs_ioc(kind: u8, n: c_int) -> c_int {
+    CTFS_PREFIX | (kind as c_int) << 8 | n
+}
+
+/*
+ * Requests for template files:
+ */
+pub const CT_TACTIVATE: c_int = ctfs_ioc(b't', 1);
+pub const CT_TCLEAR: c_int = ctfs_ioc(b't', 2);
+pub const CT_TCREATE: c_int = ctfs_ioc(b't', 3);
+pub const CT_TSET: c_int = ctfs_ioc(b't', 4);
+pub const CT_TGET: c_int = ctfs_ioc(b't', 5);
+
+/*
+ * Requests for control files:
+ */
+pub const CT_CABANDON: c_int = ctfs_ioc(b'c', 1);
+pub const CT_CACK: c_int = ctfs_ioc(b'c', 2);
+pub const CT_CQREQ: c_int = ctfs_ioc(b'c', 3);
+pub const CT_CADOPT: c_int = ctfs_ioc(b'c', 4);
+pub const CT_CNEWCT: c_int = ctfs_ioc(b'c', 5);
+pub const CT_CNACK: c_int = ctfs_ioc(b'c', 6);
+
+/*
+ * Requests for status files:
+ */
+pub const CT_SSTATUS: c_int = ctfs_ioc(b's', 1);
+pub const CT_SCOOKIE: c_int = ctfs_ioc(b's', 2);
+
+/*
+ * Requests for event endpoints, including bundles:
+ */
+pub const CT_ERESET: c_int = ctfs_ioc(b'e', 1);
+pub const CT_ERECV: c_int = ctfs_ioc(b'e', 2);
+pub const CT_ECRECV: c_int = ctfs_ioc(b'e', 3);
+pub const CT_ENEXT: c_int = ctfs_ioc(b'e', 4);
+pub const CT_ERELIABLE: c_int = ctfs_ioc(b'e', 5);
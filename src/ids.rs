@@ -0,0 +1,130 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use crate::{ctevid_t, ctid_t, zoneid_t};
+
+/*
+ * Defines a transparent wrapper around a raw ID type, so that IDs of one kind
+ * cannot be mistaken for another, or for process IDs, cookies and the like.
+ */
+macro_rules! id_type {
+    ($(#[$attr:meta])* pub struct $name:ident($raw:ty);) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(transparent)
+        )]
+        #[repr(transparent)]
+        pub struct $name(pub $raw);
+
+        impl $name {
+            pub const fn as_raw(self) -> $raw {
+                self.0
+            }
+        }
+
+        impl From<$raw> for $name {
+            fn from(id: $raw) -> $name {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for $raw {
+            fn from(id: $name) -> $raw {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> Result<$name, ParseIntError> {
+                s.parse().map($name)
+            }
+        }
+    };
+}
+
+id_type! {
+    /// The ID of a contract, as used to name it in /system/contract and
+    /// returned by ct_status_get_id(3CONTRACT).
+    pub struct ContractId(ctid_t);
+}
+
+id_type! {
+    /// The ID of a contract event, as returned by
+    /// ct_event_get_evid(3CONTRACT).  Event IDs are unique across all
+    /// contracts.
+    pub struct EventId(ctevid_t);
+}
+
+id_type! {
+    /// The ID of a zone, as returned by ct_status_get_zoneid(3CONTRACT).
+    pub struct ZoneId(zoneid_t);
+}
+
+/*
+ * The global zone always has zone ID 0; see sys/zone.h:
+ */
+pub const GLOBAL_ZONEID: ZoneId = ZoneId(0);
+
+impl ZoneId {
+    pub const fn is_global(self) -> bool {
+        self.0 == GLOBAL_ZONEID.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_id() {
+        let zoneid: ZoneId = "0".parse().unwrap();
+        assert_eq!(zoneid, GLOBAL_ZONEID);
+        assert!(zoneid.is_global());
+        assert!(!ZoneId(3).is_global());
+        assert_eq!(ZoneId(3).to_string(), "3");
+        assert_eq!(zoneid_t::from(ZoneId(2)), 2);
+        assert!("1.5".parse::<ZoneId>().is_err());
+    }
+
+    #[test]
+    fn contract_id() {
+        let ctid: ContractId = "42".parse().unwrap();
+        assert_eq!(ctid, ContractId(42));
+        assert_eq!(ctid.as_raw(), 42);
+        assert_eq!(ctid_t::from(ctid), 42);
+        assert_eq!(ContractId::from(42), ctid);
+        assert_eq!(ctid.to_string(), "42");
+        assert!(ContractId(7) < ctid);
+
+        for bad in ["", " 42", "ct42", "4 2", "2147483648"] {
+            assert!(bad.parse::<ContractId>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn event_id() {
+        let evid: EventId = "18446744073709551615".parse().unwrap();
+        assert_eq!(evid, EventId(u64::MAX));
+        assert_eq!(evid.to_string(), "18446744073709551615");
+        assert_eq!(ctevid_t::from(EventId(9)), 9);
+        assert!(EventId(9) < evid);
+        assert!("-1".parse::<EventId>().is_err());
+        assert!("18446744073709551616".parse::<EventId>().is_err());
+    }
+}
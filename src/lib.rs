@@ -5,13 +5,83 @@
 #![allow(non_camel_case_types)]
 #![allow(dead_code)]
 
+use std::fmt;
 use std::marker::{PhantomData, PhantomPinned};
 use std::os::raw::{c_char, c_int, c_uint};
 
 #[cfg(feature = "private")]
 use libc::c_void;
-use libc::{ctid_t, id_t, pid_t, size_t, zoneid_t};
-use num_derive::{FromPrimitive, ToPrimitive};
+use libc::{id_t, pid_t, size_t};
+use num_traits::{FromPrimitive, ToPrimitive};
+
+pub mod compat;
+pub mod ctfs;
+#[cfg(feature = "private")]
+pub mod ctfs_ioctl;
+pub mod ids;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod safe;
+#[cfg(all(not(libcontract), not(feature = "mock")))]
+mod stub;
+
+#[cfg(libcontract)]
+pub use libc::{ctid_t, zoneid_t};
+
+/*
+ * The libc crate only defines these types for systems that have contracts.
+ * Elsewhere, they are defined here as they are on illumos, so that the crate
+ * still builds; see the "stub" module.
+ */
+#[cfg(not(libcontract))]
+pub type ctid_t = c_int;
+#[cfg(not(libcontract))]
+pub type zoneid_t = c_int;
+
+/*
+ * Declares the libcontract(3LIB) functions, which the build script arranges
+ * to be linked against when the "libcontract" cfg is set.  On systems
+ * without contracts, each function is instead defined by the "stub" module
+ * to return ENOTSUP, or a zero value for return types other than c_int.  With
+ * the "mock" feature, each function instead forwards to the simulation in
+ * the "mock" module, on any system.
+ */
+macro_rules! libcontract_fns {
+    (
+        $(pub fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;)*
+    ) => {
+        #[cfg(all(libcontract, not(feature = "mock")))]
+        extern "C" {
+            $(pub fn $name($($arg: $ty),*) $(-> $ret)?;)*
+        }
+
+        $(
+            /// A stand-in for the libcontract(3LIB) function of the same
+            /// name on a system without contracts.
+            ///
+            /// # Safety
+            ///
+            /// The arguments are not used, but callers must uphold the same
+            /// requirements as for the real function.
+            #[cfg(all(not(libcontract), not(feature = "mock")))]
+            #[allow(unused_variables)]
+            pub unsafe extern "C" fn $name($($arg: $ty),*) $(-> $ret)? {
+                stub::Stub::stub()
+            }
+
+            /// The simulated version of the libcontract(3LIB) function of
+            /// the same name; see the [`mock`] module.
+            ///
+            /// # Safety
+            ///
+            /// As for the real function.
+            #[cfg(feature = "mock")]
+            pub unsafe extern "C" fn $name($($arg: $ty),*) $(-> $ret)? {
+                mock::ffi::$name($($arg),*)
+            }
+        )*
+    };
+}
 
 macro_rules! opaque_handle {
     ($type_name:ident) => {
@@ -34,6 +104,11 @@ macro_rules! opaque_handle {
     };
 }
 
+/*
+ * In libcontract.h, ct_stathdl_t and ct_evthdl_t are typedefs for "void *".
+ * Here they are opaque types instead, and a handle is always a "*mut" pointer
+ * to one of them; e.g., "ct_stathdl_t hdl" in C is "hdl: *mut ct_stathdl_t".
+ */
 opaque_handle!(ct_stathdl_t);
 opaque_handle!(ct_evthdl_t);
 
@@ -56,6 +131,27 @@ pub const CT_EV_NEGEND: c_uint = 0;
  */
 pub const CT_PARAM_MAX_SIZE: usize = 8192;
 
+/*
+ * Template parameter identifiers, for the "ctpm_id" member of ct_param_t;
+ * see sys/contract.h and sys/contract/process.h:
+ */
+#[cfg(feature = "private")]
+pub const CTP_COOKIE: u32 = 0;
+#[cfg(feature = "private")]
+pub const CTP_EV_INFO: u32 = 1;
+#[cfg(feature = "private")]
+pub const CTP_EV_CRITICAL: u32 = 2;
+#[cfg(feature = "private")]
+pub const CTPP_SUBSUME: u32 = 0x100;
+#[cfg(feature = "private")]
+pub const CTPP_PARAMS: u32 = 0x101;
+#[cfg(feature = "private")]
+pub const CTPP_EV_FATAL: u32 = 0x102;
+#[cfg(feature = "private")]
+pub const CTPP_SVC_FMRI: u32 = 0x103;
+#[cfg(feature = "private")]
+pub const CTPP_CREATOR_AUX: u32 = 0x104;
+
 /*
  * Values returned by ct_event_get_flags(3CONTRACT):
  */
@@ -63,24 +159,155 @@ pub const CTE_ACK: c_uint = 0x1;
 pub const CTE_INFO: c_uint = 0x2;
 pub const CTE_NEG: c_uint = 0x4;
 
-#[derive(Debug, FromPrimitive, ToPrimitive, Clone, Copy)]
-#[repr(C)]
-pub enum ctstate_t {
-    CTS_OWNED,
-    CTS_INHERITED,
-    CTS_ORPHAN,
-    CTS_DEAD,
+/*
+ * The kernel may return values for these types that this crate does not yet
+ * know about, which would be undefined behaviour for a Rust enum.  They are
+ * instead represented as transparent newtypes with an associated constant
+ * for each known value, so that they can still be matched on in the same way
+ * as an enum.
+ */
+macro_rules! open_enum {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            $(const $value:ident = $n:expr;)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(transparent)
+        )]
+        #[repr(transparent)]
+        pub struct $name(pub c_int);
+
+        impl $name {
+            $(pub const $value: $name = $name($n);)*
+
+            /// Returns the name of this value, if it is known.
+            pub const fn name(self) -> Option<&'static str> {
+                match self {
+                    $($name::$value => Some(stringify!($value)),)*
+                    _ => None,
+                }
+            }
+
+            pub const fn is_known(self) -> bool {
+                self.name().is_some()
+            }
+        }
+
+        impl TryFrom<c_int> for $name {
+            type Error = UnknownValue;
+
+            fn try_from(n: c_int) -> Result<$name, UnknownValue> {
+                let v = $name(n);
+                if v.is_known() {
+                    Ok(v)
+                } else {
+                    Err(UnknownValue(n))
+                }
+            }
+        }
+
+        impl From<$name> for c_int {
+            fn from(v: $name) -> c_int {
+                v.0
+            }
+        }
+
+        /*
+         * Unlike TryFrom, this accepts unknown values that fit in a c_int,
+         * so that any value produced by ToPrimitive converts back to itself.
+         */
+        impl FromPrimitive for $name {
+            fn from_i64(n: i64) -> Option<$name> {
+                c_int::try_from(n).ok().map($name)
+            }
+
+            fn from_u64(n: u64) -> Option<$name> {
+                c_int::try_from(n).ok().map($name)
+            }
+        }
+
+        impl ToPrimitive for $name {
+            fn to_i64(&self) -> Option<i64> {
+                Some(self.0.into())
+            }
+
+            fn to_u64(&self) -> Option<u64> {
+                u64::try_from(self.0).ok()
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.name() {
+                    Some(name) => write!(f, "{}", name),
+                    None => write!(f, "{}({})", stringify!($name), self.0),
+                }
+            }
+        }
+    };
 }
 
-#[derive(Debug, FromPrimitive, ToPrimitive, Clone, Copy)]
-#[repr(C)]
-pub enum ct_typeid_t {
-    CTT_PROCESS,
-    CTT_DEVICE,
+/// The error returned when converting a value that does not correspond to
+/// any known value of a type such as [`ctstate_t`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownValue(pub c_int);
+
+impl fmt::Display for UnknownValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown value {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownValue {}
+
+open_enum! {
+    /// The state of a contract; see ct_status_get_state(3CONTRACT).
+    pub struct ctstate_t {
+        const CTS_OWNED = 0;
+        const CTS_INHERITED = 1;
+        const CTS_ORPHAN = 2;
+        const CTS_DEAD = 3;
+    }
+}
+
+open_enum! {
+    /// A contract type, as found in the private ct_event_t and ct_status_t
+    /// structures.  Contract types added to the system after this crate
+    /// are represented by their numeric value.
+    pub struct ct_typeid_t {
+        const CTT_PROCESS = 0;
+        const CTT_DEVICE = 1;
+    }
+}
+
+impl ct_typeid_t {
+    /// Returns the name of this contract type as used in ctfs(4FS) and
+    /// returned by ct_status_get_type(3CONTRACT), if it is known.
+    pub const fn type_name(self) -> Option<&'static str> {
+        match self {
+            ct_typeid_t::CTT_PROCESS => Some(ctfs::PROCESS_TYPE),
+            ct_typeid_t::CTT_DEVICE => Some(ctfs::DEVICE_TYPE),
+            _ => None,
+        }
+    }
+
+    pub fn from_type_name(name: &str) -> Option<ct_typeid_t> {
+        match name {
+            ctfs::PROCESS_TYPE => Some(ct_typeid_t::CTT_PROCESS),
+            ctfs::DEVICE_TYPE => Some(ct_typeid_t::CTT_DEVICE),
+            _ => None,
+        }
+    }
 }
 
 /*
- * Process contract event types; see process(5), ct_event_get_flags(CONTRACT),
+ * Process contract event types; see process(5), ct_event_get_type(3CONTRACT),
  * ct_tmpl_set_critical(3CONTRACT), etc:
  */
 pub const CT_PR_EV_EMPTY: c_uint = 0x1;
@@ -101,10 +328,13 @@ pub const CT_PR_INHERIT: c_uint = 0x1;
 pub const CT_PR_NOORPHAN: c_uint = 0x2;
 pub const CT_PR_PGRPONLY: c_uint = 0x4;
 pub const CT_PR_REGENT: c_uint = 0x8;
-pub const CT_PR_ALLPARAM: c_uint = 0xf;
+pub const CT_PR_KEEP_EXEC: c_uint = 0x10;
+pub const CT_PR_ALLPARAM: c_uint = 0x1f;
 
 /*
- * Device contract event types:
+ * Device contract event types.  These values also represent device states,
+ * both in acceptable state sets (see ct_dev_tmpl_set_aset(3CONTRACT)) and as
+ * returned by ct_dev_status_get_dev_state(3CONTRACT):
  */
 pub const CT_DEV_EV_ONLINE: c_uint = 0x1;
 pub const CT_DEV_EV_DEGRADED: c_uint = 0x2;
@@ -127,6 +357,47 @@ pub struct ct_event_t {
     pub ctev_buffer: *mut c_char,
 }
 
+#[cfg(feature = "private")]
+impl ct_event_t {
+    /// Returns the entire event buffer, or `None` if the buffer pointer is
+    /// NULL.
+    ///
+    /// # Safety
+    ///
+    /// `ctev_buffer` must either be NULL or point to at least `ctev_nbytes`
+    /// bytes that remain valid, and are not written to, for the lifetime of
+    /// the returned slice.
+    pub unsafe fn buffer(&self) -> Option<&[u8]> {
+        if self.ctev_buffer.is_null() {
+            return None;
+        }
+
+        Some(std::slice::from_raw_parts(
+            self.ctev_buffer as *const u8,
+            self.ctev_nbytes as usize,
+        ))
+    }
+
+    /// Splits the event buffer at `ctev_goffset` into the portion visible to
+    /// all readers and the portion that is only provided to readers in the
+    /// global zone (which is empty otherwise).  Returns `None` if the buffer
+    /// pointer is NULL or the offset lies beyond the end of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// As for [`ct_event_t::buffer`].
+    pub unsafe fn split_buffer(&self) -> Option<(&[u8], &[u8])> {
+        let buf = self.buffer()?;
+        let goffset = self.ctev_goffset as usize;
+
+        if goffset > buf.len() {
+            return None;
+        }
+
+        Some(buf.split_at(goffset))
+    }
+}
+
 #[cfg(feature = "private")]
 #[derive(Debug)]
 #[repr(C)]
@@ -157,8 +428,7 @@ pub struct ct_param_t {
     pub ctpm_value: *mut c_void,
 }
 
-#[link(name = "contract")]
-extern "C" {
+libcontract_fns! {
     /*
      * Common contract template functions:
      */
@@ -265,35 +535,40 @@ extern "C" {
      * Process contract event functions:
      */
 
-    pub fn ct_pr_event_get_pid(evthdl: ct_evthdl_t, pidp: *mut pid_t) -> c_int;
-    pub fn ct_pr_event_get_ppid(evthdl: ct_evthdl_t, pidp: *mut pid_t)
-        -> c_int;
+    pub fn ct_pr_event_get_pid(
+        evthdl: *mut ct_evthdl_t,
+        pidp: *mut pid_t,
+    ) -> c_int;
+    pub fn ct_pr_event_get_ppid(
+        evthdl: *mut ct_evthdl_t,
+        pidp: *mut pid_t,
+    ) -> c_int;
     pub fn ct_pr_event_get_signal(
-        evthdl: ct_evthdl_t,
+        evthdl: *mut ct_evthdl_t,
         signalp: *mut c_int,
     ) -> c_int;
     pub fn ct_pr_event_get_sender(
-        evthdl: ct_evthdl_t,
+        evthdl: *mut ct_evthdl_t,
         pidp: *mut pid_t,
     ) -> c_int;
     pub fn ct_pr_event_get_senderct(
-        evthdl: ct_evthdl_t,
+        evthdl: *mut ct_evthdl_t,
         ctidp: *mut ctid_t,
     ) -> c_int;
     pub fn ct_pr_event_get_exitstatus(
-        evthdl: ct_evthdl_t,
+        evthdl: *mut ct_evthdl_t,
         statusp: *mut c_int,
     ) -> c_int;
     pub fn ct_pr_event_get_pcorefile(
-        evthdl: ct_evthdl_t,
+        evthdl: *mut ct_evthdl_t,
         namep: *mut *mut c_char,
     ) -> c_int;
     pub fn ct_pr_event_get_gcorefile(
-        evthdl: ct_evthdl_t,
+        evthdl: *mut ct_evthdl_t,
         namep: *mut *mut c_char,
     ) -> c_int;
     pub fn ct_pr_event_get_zcorefile(
-        evthdl: ct_evthdl_t,
+        evthdl: *mut ct_evthdl_t,
         namep: *mut *mut c_char,
     ) -> c_int;
 
@@ -302,37 +577,37 @@ extern "C" {
      */
 
     pub fn ct_pr_status_get_param(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         paramp: *mut c_uint,
     ) -> c_int;
     pub fn ct_pr_status_get_fatal(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         eventsp: *mut c_uint,
     ) -> c_int;
     pub fn ct_pr_status_get_members(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         pidpp: *mut *mut pid_t,
         n: *mut c_uint,
     ) -> c_int;
     pub fn ct_pr_status_get_contracts(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         idpp: *mut *mut ctid_t,
         n: *mut c_uint,
     ) -> c_int;
     pub fn ct_pr_status_get_svc_fmri(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         fmri: *mut *mut c_char,
     ) -> c_int;
     pub fn ct_pr_status_get_svc_aux(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         aux: *mut *mut c_char,
     ) -> c_int;
     pub fn ct_pr_status_get_svc_ctid(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         ctid: *mut ctid_t,
     ) -> c_int;
     pub fn ct_pr_status_get_svc_creator(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         creator: *mut *mut c_char,
     ) -> c_int;
 
@@ -359,19 +634,130 @@ extern "C" {
      */
 
     pub fn ct_dev_status_get_dev_state(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         statep: *mut c_uint,
     ) -> c_int;
     pub fn ct_dev_status_get_aset(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         asetp: *mut c_uint,
     ) -> c_int;
     pub fn ct_dev_status_get_minor(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         minorp: *mut *mut c_char,
     ) -> c_int;
     pub fn ct_dev_status_get_noneg(
-        stathdl: *const ct_stathdl_t,
+        stathdl: *mut ct_stathdl_t,
         nonegp: *mut c_uint,
     ) -> c_int;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_try_from() {
+        assert_eq!(ctstate_t::try_from(2), Ok(ctstate_t::CTS_ORPHAN));
+        assert_eq!(c_int::from(ctstate_t::CTS_DEAD), 3);
+        assert_eq!(ctstate_t::try_from(4), Err(UnknownValue(4)));
+        assert_eq!(ctstate_t::try_from(-1), Err(UnknownValue(-1)));
+        assert_eq!(UnknownValue(4).to_string(), "unknown value 4");
+    }
+
+    #[test]
+    fn state_names() {
+        assert_eq!(ctstate_t::CTS_INHERITED.name(), Some("CTS_INHERITED"));
+        assert!(ctstate_t::CTS_OWNED.is_known());
+        assert!(!ctstate_t(4).is_known());
+        assert_eq!(ctstate_t(4).name(), None);
+        assert_eq!(format!("{:?}", ctstate_t::CTS_DEAD), "CTS_DEAD");
+        assert_eq!(format!("{:?}", ctstate_t(4)), "ctstate_t(4)");
+    }
+
+    #[test]
+    fn state_primitive_round_trip() {
+        for state in [ctstate_t::CTS_OWNED, ctstate_t(4), ctstate_t(-1)] {
+            let n = state.to_i64().unwrap();
+            assert_eq!(ctstate_t::from_i64(n), Some(state));
+        }
+
+        assert_eq!(ctstate_t(4).to_u64(), Some(4));
+        assert_eq!(ctstate_t::from_u64(4), Some(ctstate_t(4)));
+        assert_eq!(ctstate_t(-1).to_u64(), None);
+        assert_eq!(ctstate_t::from_i64(i64::from(c_int::MAX) + 1), None);
+        assert_eq!(ctstate_t::from_u64(u64::MAX), None);
+    }
+
+    #[test]
+    fn type_names() {
+        for ty in [ct_typeid_t::CTT_PROCESS, ct_typeid_t::CTT_DEVICE] {
+            assert_eq!(
+                ct_typeid_t::from_type_name(ty.type_name().unwrap()),
+                Some(ty)
+            );
+        }
+        assert_eq!(
+            ct_typeid_t::from_type_name("process"),
+            Some(ct_typeid_t::CTT_PROCESS)
+        );
+        assert_eq!(
+            ct_typeid_t::from_type_name("device"),
+            Some(ct_typeid_t::CTT_DEVICE)
+        );
+        assert_eq!(ct_typeid_t::from_type_name("Process"), None);
+        assert_eq!(ct_typeid_t::from_type_name(""), None);
+        assert_eq!(ct_typeid_t(2).type_name(), None);
+    }
+
+    #[test]
+    fn unknown_type() {
+        assert_eq!(ct_typeid_t::try_from(2), Err(UnknownValue(2)));
+        assert_eq!(format!("{:?}", ct_typeid_t(2)), "ct_typeid_t(2)");
+        assert_eq!(ct_typeid_t::from_i64(2), Some(ct_typeid_t(2)));
+        assert_eq!(ct_typeid_t(2).to_i64(), Some(2));
+    }
+
+    #[cfg(feature = "private")]
+    fn event(buf: &mut [u8], goffset: u32) -> ct_event_t {
+        ct_event_t {
+            ctev_id: 1,
+            ctev_pad1: 0,
+            ctev_evid: 2,
+            ctev_cttype: ct_typeid_t::CTT_PROCESS,
+            ctev_flags: 0,
+            ctev_type: CT_PR_EV_EXIT,
+            ctev_nbytes: buf.len() as u32,
+            ctev_goffset: goffset,
+            ctev_pad2: 0,
+            ctev_buffer: buf.as_mut_ptr().cast(),
+        }
+    }
+
+    #[cfg(feature = "private")]
+    #[test]
+    fn split_buffer() {
+        let mut buf = *b"commonglobal";
+
+        let ev = event(&mut buf, 6);
+        let (common, global) = unsafe { ev.split_buffer() }.unwrap();
+        assert_eq!(common, b"common");
+        assert_eq!(global, b"global");
+
+        let ev = event(&mut buf, 12);
+        let (common, global) = unsafe { ev.split_buffer() }.unwrap();
+        assert_eq!(common, b"commonglobal");
+        assert!(global.is_empty());
+
+        let ev = event(&mut buf, 13);
+        assert!(unsafe { ev.split_buffer() }.is_none());
+    }
+
+    #[cfg(feature = "private")]
+    #[test]
+    fn split_buffer_null() {
+        let mut ev = event(&mut [], 0);
+        ev.ctev_buffer = std::ptr::null_mut();
+        assert!(unsafe { ev.buffer() }.is_none());
+        assert!(unsafe { ev.split_buffer() }.is_none());
+    }
+}
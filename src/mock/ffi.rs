@@ -0,0 +1,820 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+/*
+ * The simulated libcontract(3LIB) functions, which the declarations in the
+ * crate root forward to with the "mock" feature.  As in the real library,
+ * functions that can fail return an error number, and handles are pointers
+ * to memory owned by the library until freed; here, a boxed copy of the
+ * event or status.
+ */
+
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr;
+
+use libc::{id_t, pid_t, size_t};
+
+use super::{engine, holder_pid, Engine, MockEvent, Object, Terms, ACTIVE};
+use crate::{ct_evthdl_t, ct_stathdl_t, ctevid_t, ctid_t, ctstate_t, zoneid_t};
+
+/*
+ * The status of a contract, as returned by ct_status_read().
+ */
+struct MockStatus {
+    id: ctid_t,
+    detail: c_int,
+    state: ctstate_t,
+    holder: id_t,
+    nevents: c_int,
+    terms: Terms,
+    members: Vec<pid_t>,
+    contracts: Vec<ctid_t>,
+    svc_fmri: CString,
+    svc_aux: CString,
+    svc_creator: CString,
+}
+
+unsafe fn event<'a>(hdl: *mut ct_evthdl_t) -> &'a MockEvent {
+    &*hdl.cast::<MockEvent>()
+}
+
+unsafe fn status<'a>(hdl: *mut ct_stathdl_t) -> &'a MockStatus {
+    &*hdl.cast::<MockStatus>()
+}
+
+/*
+ * Stores a value that an event or status may not carry, failing with ENOENT
+ * as libcontract does for a field that is not present.
+ */
+unsafe fn put<T>(p: *mut T, v: Option<T>) -> c_int {
+    match v {
+        Some(v) => {
+            *p = v;
+            0
+        }
+        None => libc::ENOENT,
+    }
+}
+
+unsafe fn put_str(p: *mut *mut c_char, s: Option<&CStr>) -> c_int {
+    put(p, s.map(|s| s.as_ptr().cast_mut()))
+}
+
+fn with_template(fd: c_int, f: impl FnOnce(&mut Terms) -> c_int) -> c_int {
+    match engine().object(fd) {
+        Some(Object::Template(terms)) => f(terms),
+        _ => libc::EBADF,
+    }
+}
+
+fn set_events(
+    fd: c_int,
+    allowed: c_uint,
+    f: fn(&mut Terms) -> &mut c_uint,
+    events: c_uint,
+) -> c_int {
+    if events & !allowed != 0 {
+        return libc::EINVAL;
+    }
+    with_template(fd, |t| {
+        *f(t) = events;
+        0
+    })
+}
+
+unsafe fn set_str(
+    fd: c_int,
+    f: fn(&mut Terms) -> &mut Option<CString>,
+    s: *const c_char,
+) -> c_int {
+    let s = CStr::from_ptr(s).to_owned();
+    with_template(fd, |t| {
+        *f(t) = Some(s);
+        0
+    })
+}
+
+/*
+ * Copies a template string in the manner of strlcpy(3C), returning its
+ * length, or -1 with errno set on failure.
+ */
+unsafe fn get_str(
+    fd: c_int,
+    f: fn(&Terms) -> Option<&CString>,
+    buf: *mut c_char,
+    size: size_t,
+) -> c_int {
+    let mut s = None;
+    let rv = with_template(fd, |t| {
+        s = Some(f(t).cloned().unwrap_or_default());
+        0
+    });
+    let Some(s) = s else {
+        set_errno(rv);
+        return -1;
+    };
+
+    let bytes = s.as_bytes();
+    if size > 0 {
+        let n = bytes.len().min(size - 1);
+        ptr::copy_nonoverlapping(bytes.as_ptr().cast(), buf, n);
+        *buf.add(n) = 0;
+    }
+    bytes.len() as c_int
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn set_errno(e: c_int) {
+    *libc::__errno_location() = e;
+}
+
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+unsafe fn set_errno(e: c_int) {
+    *libc::___errno() = e;
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "illumos",
+    target_os = "solaris"
+)))]
+unsafe fn set_errno(e: c_int) {
+    *libc::__error() = e;
+}
+
+fn with_ctl(fd: c_int, f: impl FnOnce(&mut Engine, ctid_t) -> c_int) -> c_int {
+    let mut eng = engine();
+    match eng.object(fd) {
+        Some(&mut Object::Ctl(ctid)) => f(&mut eng, ctid),
+        _ => libc::EBADF,
+    }
+}
+
+/*
+ * Common contract template functions:
+ */
+
+pub(crate) unsafe fn ct_tmpl_activate(fd: c_int) -> c_int {
+    with_template(fd, |t| {
+        ACTIVE.set(Some(t.clone()));
+        0
+    })
+}
+
+pub(crate) unsafe fn ct_tmpl_clear(fd: c_int) -> c_int {
+    with_template(fd, |_| {
+        ACTIVE.set(None);
+        0
+    })
+}
+
+pub(crate) unsafe fn ct_tmpl_create(fd: c_int, idp: *mut ctid_t) -> c_int {
+    let mut eng = engine();
+    let terms = match eng.object(fd) {
+        Some(Object::Template(terms)) => terms.clone(),
+        _ => return libc::EBADF,
+    };
+    *idp = eng.create(terms);
+    0
+}
+
+pub(crate) unsafe fn ct_tmpl_set_cookie(fd: c_int, cookie: u64) -> c_int {
+    with_template(fd, |t| {
+        t.cookie = cookie;
+        0
+    })
+}
+
+pub(crate) unsafe fn ct_tmpl_set_critical(fd: c_int, events: c_uint) -> c_int {
+    set_events(fd, crate::CT_PR_ALLEVENT, |t| &mut t.critical, events)
+}
+
+pub(crate) unsafe fn ct_tmpl_set_informative(
+    fd: c_int,
+    events: c_uint,
+) -> c_int {
+    set_events(fd, crate::CT_PR_ALLEVENT, |t| &mut t.informative, events)
+}
+
+pub(crate) unsafe fn ct_tmpl_get_cookie(fd: c_int, cookiep: *mut u64) -> c_int {
+    with_template(fd, |t| put(cookiep, Some(t.cookie)))
+}
+
+pub(crate) unsafe fn ct_tmpl_get_critical(
+    fd: c_int,
+    eventsp: *mut c_uint,
+) -> c_int {
+    with_template(fd, |t| put(eventsp, Some(t.critical)))
+}
+
+pub(crate) unsafe fn ct_tmpl_get_informative(
+    fd: c_int,
+    eventsp: *mut c_uint,
+) -> c_int {
+    with_template(fd, |t| put(eventsp, Some(t.informative)))
+}
+
+/*
+ * Common contract control functions:
+ */
+
+pub(crate) unsafe fn ct_ctl_adopt(fd: c_int) -> c_int {
+    with_ctl(fd, |eng, ctid| {
+        let Some(ct) = eng.contract(ctid) else {
+            return libc::ESRCH;
+        };
+        match ct.state {
+            ctstate_t::CTS_ORPHAN | ctstate_t::CTS_INHERITED => {
+                ct.state = ctstate_t::CTS_OWNED;
+                ct.holder = holder_pid();
+                0
+            }
+            ctstate_t::CTS_OWNED => libc::EBUSY,
+            _ => libc::EACCES,
+        }
+    })
+}
+
+pub(crate) unsafe fn ct_ctl_abandon(fd: c_int) -> c_int {
+    with_ctl(fd, |eng, ctid| eng.abandon(ctid, holder_pid()))
+}
+
+/*
+ * Process contracts are never negotiated, so there is no event for which a
+ * new contract could be requested.
+ */
+pub(crate) unsafe fn ct_ctl_newct(
+    fd: c_int,
+    _evid: ctevid_t,
+    _templatefd: c_int,
+) -> c_int {
+    with_ctl(fd, |_, _| libc::ESRCH)
+}
+
+pub(crate) unsafe fn ct_ctl_ack(fd: c_int, evid: ctevid_t) -> c_int {
+    with_ctl(fd, |eng, ctid| {
+        let Some(ct) = eng.contract(ctid) else {
+            return libc::ESRCH;
+        };
+        match ct.events.iter_mut().find(|ev| ev.evid == evid) {
+            Some(ev) if ev.critical && !ev.acked => {
+                ev.acked = true;
+                0
+            }
+            _ => libc::ESRCH,
+        }
+    })
+}
+
+pub(crate) unsafe fn ct_ctl_nack(fd: c_int, _evid: ctevid_t) -> c_int {
+    with_ctl(fd, |_, _| libc::ESRCH)
+}
+
+pub(crate) unsafe fn ct_ctl_qack(fd: c_int, _evid: ctevid_t) -> c_int {
+    with_ctl(fd, |_, _| libc::ESRCH)
+}
+
+/*
+ * Common contract status functions:
+ */
+
+pub(crate) unsafe fn ct_status_read(
+    fd: c_int,
+    detail: c_int,
+    stathdlp: *mut *mut ct_stathdl_t,
+) -> c_int {
+    if !(crate::CTD_COMMON..=crate::CTD_ALL).contains(&detail) {
+        return libc::EINVAL;
+    }
+
+    let mut eng = engine();
+    let ctid = match eng.object(fd) {
+        Some(&mut Object::Status(ctid)) => ctid,
+        _ => return libc::EBADF,
+    };
+    let Some(ct) = eng.contracts.get(&ctid) else {
+        return libc::ESRCH;
+    };
+
+    let st = MockStatus {
+        id: ctid,
+        detail,
+        state: ct.state,
+        holder: ct.holder,
+        nevents: ct.events.iter().filter(|ev| ev.critical && !ev.acked).count()
+            as c_int,
+        terms: ct.terms.clone(),
+        members: ct.members.clone(),
+        contracts: Vec::new(),
+        svc_fmri: ct.terms.svc_fmri.clone().unwrap_or_default(),
+        svc_aux: ct.terms.svc_aux.clone().unwrap_or_default(),
+        svc_creator: c"mock".into(),
+    };
+    *stathdlp = Box::into_raw(Box::new(st)).cast();
+    0
+}
+
+pub(crate) unsafe fn ct_status_free(stathdl: *mut ct_stathdl_t) {
+    if !stathdl.is_null() {
+        drop(Box::from_raw(stathdl.cast::<MockStatus>()));
+    }
+}
+
+pub(crate) unsafe fn ct_status_get_id(stathdl: *mut ct_stathdl_t) -> ctid_t {
+    status(stathdl).id
+}
+
+pub(crate) unsafe fn ct_status_get_zoneid(
+    _stathdl: *mut ct_stathdl_t,
+) -> zoneid_t {
+    0
+}
+
+pub(crate) unsafe fn ct_status_get_type(
+    _stathdl: *mut ct_stathdl_t,
+) -> *const c_char {
+    c"process".as_ptr()
+}
+
+pub(crate) unsafe fn ct_status_get_state(
+    stathdl: *mut ct_stathdl_t,
+) -> ctstate_t {
+    status(stathdl).state
+}
+
+pub(crate) unsafe fn ct_status_get_holder(stathdl: *mut ct_stathdl_t) -> id_t {
+    status(stathdl).holder
+}
+
+pub(crate) unsafe fn ct_status_get_nevents(
+    stathdl: *mut ct_stathdl_t,
+) -> c_int {
+    status(stathdl).nevents
+}
+
+/*
+ * There are no negotiations, and so never a time remaining for one.
+ */
+pub(crate) unsafe fn ct_status_get_ntime(_stathdl: *mut ct_stathdl_t) -> c_int {
+    -1
+}
+
+pub(crate) unsafe fn ct_status_get_qtime(_stathdl: *mut ct_stathdl_t) -> c_int {
+    -1
+}
+
+pub(crate) unsafe fn ct_status_get_nevid(
+    _stathdl: *mut ct_stathdl_t,
+) -> ctevid_t {
+    0
+}
+
+pub(crate) unsafe fn ct_status_get_cookie(stathdl: *mut ct_stathdl_t) -> u64 {
+    status(stathdl).terms.cookie
+}
+
+pub(crate) unsafe fn ct_status_get_informative(
+    stathdl: *mut ct_stathdl_t,
+) -> c_uint {
+    status(stathdl).terms.informative
+}
+
+pub(crate) unsafe fn ct_status_get_critical(
+    stathdl: *mut ct_stathdl_t,
+) -> c_uint {
+    status(stathdl).terms.critical
+}
+
+/*
+ * Common contract event functions:
+ */
+
+unsafe fn read_event(
+    fd: c_int,
+    evthndlp: *mut *mut ct_evthdl_t,
+    critical_only: bool,
+) -> c_int {
+    loop {
+        if !matches!(
+            engine().object(fd),
+            Some(Object::Events { .. } | Object::Bundle { .. })
+        ) {
+            return libc::EBADF;
+        }
+
+        /*
+         * Wait for an event without holding the lock, so that other threads
+         * can cause one.  This fails with EAGAIN if the endpoint is
+         * non-blocking and nothing is queued.
+         */
+        let mut b = 0u8;
+        let n = libc::read(fd, ptr::addr_of_mut!(b).cast(), 1);
+        if n < 0 {
+            return io::Error::last_os_error().raw_os_error().unwrap();
+        }
+
+        match engine().next_event(fd) {
+            Some(ev) if ev.critical || !critical_only => {
+                *evthndlp = Box::into_raw(Box::new(ev)).cast();
+                return 0;
+            }
+            _ => continue,
+        }
+    }
+}
+
+pub(crate) unsafe fn ct_event_read(
+    fd: c_int,
+    evthndlp: *mut *mut ct_evthdl_t,
+) -> c_int {
+    read_event(fd, evthndlp, false)
+}
+
+pub(crate) unsafe fn ct_event_read_critical(
+    fd: c_int,
+    evthandlp: *mut *mut ct_evthdl_t,
+) -> c_int {
+    read_event(fd, evthandlp, true)
+}
+
+pub(crate) unsafe fn ct_event_reset(fd: c_int) -> c_int {
+    let mut eng = engine();
+    if eng.get(fd).is_none() {
+        return libc::EBADF;
+    }
+    let Engine { open, contracts, .. } = &mut *eng;
+    let Some(o) = open.get_mut(&fd) else {
+        return libc::EBADF;
+    };
+    match &mut o.object {
+        Object::Events { ctid, next } => {
+            let oldest = contracts[&*ctid].oldest_pending();
+            if oldest < *next {
+                super::wake(&o.wakeup, *next - oldest);
+                *next = oldest;
+            }
+            0
+        }
+        Object::Bundle { .. } => 0,
+        _ => libc::EBADF,
+    }
+}
+
+/*
+ * Every simulated event is delivered reliably.
+ */
+pub(crate) unsafe fn ct_event_reliable(fd: c_int) -> c_int {
+    match engine().object(fd) {
+        Some(Object::Events { .. } | Object::Bundle { .. }) => 0,
+        _ => libc::EBADF,
+    }
+}
+
+pub(crate) unsafe fn ct_event_free(evthndl: *mut ct_evthdl_t) {
+    if !evthndl.is_null() {
+        drop(Box::from_raw(evthndl.cast::<MockEvent>()));
+    }
+}
+
+pub(crate) unsafe fn ct_event_get_ctid(evthndl: *mut ct_evthdl_t) -> ctid_t {
+    event(evthndl).ctid
+}
+
+pub(crate) unsafe fn ct_event_get_evid(evthndl: *mut ct_evthdl_t) -> ctevid_t {
+    event(evthndl).evid
+}
+
+pub(crate) unsafe fn ct_event_get_flags(evthndl: *mut ct_evthdl_t) -> c_uint {
+    let ev = event(evthndl);
+    let mut flags = 0;
+    if !ev.critical {
+        flags |= crate::CTE_INFO;
+    }
+    if ev.acked {
+        flags |= crate::CTE_ACK;
+    }
+    flags
+}
+
+pub(crate) unsafe fn ct_event_get_type(evthndl: *mut ct_evthdl_t) -> c_uint {
+    event(evthndl).ty
+}
+
+/*
+ * Only the CT_EV_NEGEND events of negotiations carry these.
+ */
+pub(crate) unsafe fn ct_event_get_nevid(
+    _evthndl: *mut ct_evthdl_t,
+    _evidp: *mut ctevid_t,
+) -> c_int {
+    libc::EINVAL
+}
+
+pub(crate) unsafe fn ct_event_get_newct(
+    _evthndl: *mut ct_evthdl_t,
+    _ctidp: *mut ctid_t,
+) -> c_int {
+    libc::EINVAL
+}
+
+/*
+ * Process contract template functions:
+ */
+
+pub(crate) unsafe fn ct_pr_tmpl_set_transfer(fd: c_int, ctid: ctid_t) -> c_int {
+    with_template(fd, |t| {
+        t.transfer = ctid;
+        0
+    })
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_set_fatal(fd: c_int, events: c_uint) -> c_int {
+    set_events(fd, crate::CT_PR_ALLFATAL, |t| &mut t.fatal, events)
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_set_param(fd: c_int, params: c_uint) -> c_int {
+    set_events(fd, crate::CT_PR_ALLPARAM, |t| &mut t.params, params)
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_set_svc_fmri(
+    fd: c_int,
+    fmri: *const c_char,
+) -> c_int {
+    set_str(fd, |t| &mut t.svc_fmri, fmri)
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_set_svc_aux(
+    fd: c_int,
+    aux: *const c_char,
+) -> c_int {
+    set_str(fd, |t| &mut t.svc_aux, aux)
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_get_transfer(
+    fd: c_int,
+    ctidp: *mut ctid_t,
+) -> c_int {
+    with_template(fd, |t| put(ctidp, Some(t.transfer)))
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_get_fatal(
+    fd: c_int,
+    eventsp: *mut c_uint,
+) -> c_int {
+    with_template(fd, |t| put(eventsp, Some(t.fatal)))
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_get_param(
+    fd: c_int,
+    eventsp: *mut c_uint,
+) -> c_int {
+    with_template(fd, |t| put(eventsp, Some(t.params)))
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_get_svc_fmri(
+    fd: c_int,
+    fmri: *mut c_char,
+    size: size_t,
+) -> c_int {
+    get_str(fd, |t| t.svc_fmri.as_ref(), fmri, size)
+}
+
+pub(crate) unsafe fn ct_pr_tmpl_get_svc_aux(
+    fd: c_int,
+    aux: *mut c_char,
+    size: size_t,
+) -> c_int {
+    get_str(fd, |t| t.svc_aux.as_ref(), aux, size)
+}
+
+/*
+ * Process contract event functions:
+ */
+
+pub(crate) unsafe fn ct_pr_event_get_pid(
+    evthdl: *mut ct_evthdl_t,
+    pidp: *mut pid_t,
+) -> c_int {
+    put(pidp, Some(event(evthdl).pid))
+}
+
+pub(crate) unsafe fn ct_pr_event_get_ppid(
+    evthdl: *mut ct_evthdl_t,
+    pidp: *mut pid_t,
+) -> c_int {
+    put(pidp, event(evthdl).ppid)
+}
+
+pub(crate) unsafe fn ct_pr_event_get_signal(
+    evthdl: *mut ct_evthdl_t,
+    signalp: *mut c_int,
+) -> c_int {
+    put(signalp, event(evthdl).signal)
+}
+
+pub(crate) unsafe fn ct_pr_event_get_sender(
+    evthdl: *mut ct_evthdl_t,
+    pidp: *mut pid_t,
+) -> c_int {
+    put(pidp, event(evthdl).sender.map(|(pid, _)| pid))
+}
+
+pub(crate) unsafe fn ct_pr_event_get_senderct(
+    evthdl: *mut ct_evthdl_t,
+    ctidp: *mut ctid_t,
+) -> c_int {
+    put(ctidp, event(evthdl).sender.map(|(_, ctid)| ctid))
+}
+
+pub(crate) unsafe fn ct_pr_event_get_exitstatus(
+    evthdl: *mut ct_evthdl_t,
+    statusp: *mut c_int,
+) -> c_int {
+    put(statusp, event(evthdl).exitstatus)
+}
+
+pub(crate) unsafe fn ct_pr_event_get_pcorefile(
+    evthdl: *mut ct_evthdl_t,
+    namep: *mut *mut c_char,
+) -> c_int {
+    put_str(namep, event(evthdl).pcorefile.as_deref())
+}
+
+/*
+ * Global and zone core files are never simulated.
+ */
+pub(crate) unsafe fn ct_pr_event_get_gcorefile(
+    _evthdl: *mut ct_evthdl_t,
+    namep: *mut *mut c_char,
+) -> c_int {
+    put_str(namep, None)
+}
+
+pub(crate) unsafe fn ct_pr_event_get_zcorefile(
+    _evthdl: *mut ct_evthdl_t,
+    namep: *mut *mut c_char,
+) -> c_int {
+    put_str(namep, None)
+}
+
+/*
+ * Process contract status functions.  As in libcontract, the terms are only
+ * present in a status read with at least CTD_FIXED, and the members and
+ * inherited contracts only with CTD_ALL.
+ */
+
+unsafe fn fixed<'a>(stathdl: *mut ct_stathdl_t) -> Option<&'a MockStatus> {
+    let st = status(stathdl);
+    (st.detail >= crate::CTD_FIXED).then_some(st)
+}
+
+unsafe fn all<'a>(stathdl: *mut ct_stathdl_t) -> Option<&'a MockStatus> {
+    let st = status(stathdl);
+    (st.detail >= crate::CTD_ALL).then_some(st)
+}
+
+pub(crate) unsafe fn ct_pr_status_get_param(
+    stathdl: *mut ct_stathdl_t,
+    paramp: *mut c_uint,
+) -> c_int {
+    put(paramp, fixed(stathdl).map(|st| st.terms.params))
+}
+
+pub(crate) unsafe fn ct_pr_status_get_fatal(
+    stathdl: *mut ct_stathdl_t,
+    eventsp: *mut c_uint,
+) -> c_int {
+    put(eventsp, fixed(stathdl).map(|st| st.terms.fatal))
+}
+
+pub(crate) unsafe fn ct_pr_status_get_members(
+    stathdl: *mut ct_stathdl_t,
+    pidpp: *mut *mut pid_t,
+    n: *mut c_uint,
+) -> c_int {
+    let Some(st) = all(stathdl) else {
+        return libc::ENOENT;
+    };
+    *pidpp = st.members.as_ptr().cast_mut();
+    *n = st.members.len() as c_uint;
+    0
+}
+
+pub(crate) unsafe fn ct_pr_status_get_contracts(
+    stathdl: *mut ct_stathdl_t,
+    idpp: *mut *mut ctid_t,
+    n: *mut c_uint,
+) -> c_int {
+    let Some(st) = all(stathdl) else {
+        return libc::ENOENT;
+    };
+    *idpp = st.contracts.as_ptr().cast_mut();
+    *n = st.contracts.len() as c_uint;
+    0
+}
+
+pub(crate) unsafe fn ct_pr_status_get_svc_fmri(
+    stathdl: *mut ct_stathdl_t,
+    fmri: *mut *mut c_char,
+) -> c_int {
+    put_str(fmri, fixed(stathdl).map(|st| st.svc_fmri.as_c_str()))
+}
+
+pub(crate) unsafe fn ct_pr_status_get_svc_aux(
+    stathdl: *mut ct_stathdl_t,
+    aux: *mut *mut c_char,
+) -> c_int {
+    put_str(aux, fixed(stathdl).map(|st| st.svc_aux.as_c_str()))
+}
+
+pub(crate) unsafe fn ct_pr_status_get_svc_ctid(
+    stathdl: *mut ct_stathdl_t,
+    ctid: *mut ctid_t,
+) -> c_int {
+    put(ctid, fixed(stathdl).map(|st| st.id))
+}
+
+pub(crate) unsafe fn ct_pr_status_get_svc_creator(
+    stathdl: *mut ct_stathdl_t,
+    creator: *mut *mut c_char,
+) -> c_int {
+    put_str(creator, fixed(stathdl).map(|st| st.svc_creator.as_c_str()))
+}
+
+/*
+ * Device contracts are not simulated, and no device template can be opened,
+ * so these are never given a device template or status.
+ */
+
+pub(crate) unsafe fn ct_dev_tmpl_set_aset(_fd: c_int, _aset: c_uint) -> c_int {
+    libc::EBADF
+}
+
+pub(crate) unsafe fn ct_dev_tmpl_get_aset(
+    _fd: c_int,
+    _asetp: *mut c_uint,
+) -> c_int {
+    libc::EBADF
+}
+
+pub(crate) unsafe fn ct_dev_tmpl_set_minor(
+    _fd: c_int,
+    _minor: *const c_char,
+) -> c_int {
+    libc::EBADF
+}
+
+pub(crate) unsafe fn ct_dev_tmpl_get_minor(
+    _fd: c_int,
+    _buf: *mut c_char,
+    _buflenp: *mut size_t,
+) -> c_int {
+    libc::EBADF
+}
+
+pub(crate) unsafe fn ct_dev_tmpl_set_noneg(_fd: c_int) -> c_int {
+    libc::EBADF
+}
+
+pub(crate) unsafe fn ct_dev_tmpl_clear_noneg(_fd: c_int) -> c_int {
+    libc::EBADF
+}
+
+pub(crate) unsafe fn ct_dev_tmpl_get_noneg(
+    _fd: c_int,
+    _nonegp: *mut c_uint,
+) -> c_int {
+    libc::EBADF
+}
+
+pub(crate) unsafe fn ct_dev_status_get_dev_state(
+    _stathdl: *mut ct_stathdl_t,
+    _statep: *mut c_uint,
+) -> c_int {
+    libc::EINVAL
+}
+
+pub(crate) unsafe fn ct_dev_status_get_aset(
+    _stathdl: *mut ct_stathdl_t,
+    _asetp: *mut c_uint,
+) -> c_int {
+    libc::EINVAL
+}
+
+pub(crate) unsafe fn ct_dev_status_get_minor(
+    _stathdl: *mut ct_stathdl_t,
+    _minorp: *mut *mut c_char,
+) -> c_int {
+    libc::EINVAL
+}
+
+pub(crate) unsafe fn ct_dev_status_get_noneg(
+    _stathdl: *mut ct_stathdl_t,
+    _nonegp: *mut c_uint,
+) -> c_int {
+    libc::EINVAL
+}
@@ -0,0 +1,714 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+//! A simulated contract subsystem, for testing code that manages contracts
+//! on systems without them, or without the privileges needed to create them.
+//!
+//! With the "mock" feature, the libcontract(3LIB) functions and the contract
+//! file system are provided by this module rather than by the system, and so
+//! the types in [`crate::safe`] operate on simulated process contracts.
+//! Templates are opened, configured and activated as usual.  [`spawn`] then
+//! creates a simulated process in a new contract, as fork(2) would, and the
+//! [`MockProcess`] it returns causes the events a real process would: forks,
+//! exits, core dumps, fatal signals and hardware errors.  Those events are
+//! delivered, according to the terms of the contract, to its event endpoint
+//! and to the bundles, and are read, acknowledged and reflected in the
+//! contract's status in the same way as real ones.
+//!
+//! The simulation is shared by every thread in the process, as the contract
+//! subsystem is, so tests that run in parallel see each other's contracts
+//! when listing all contracts or reading a bundle, but do not otherwise
+//! interfere.  As on a real system, the active template and the latest
+//! contract are properties of the calling thread.
+//!
+//! Device contracts are not simulated: opening the device template fails
+//! with [`ContractError::NotSupported`].  Nor are real processes, so
+//! commands spawned with [`crate::safe::run_in_contract`] are started
+//! outside of any simulated contract, and [`crate::safe::contract_latest`]
+//! then fails.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::raw::{c_int, c_uint};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use libc::{id_t, pid_t};
+
+use crate::ctfs;
+use crate::ids::ContractId;
+use crate::safe::ContractError;
+use crate::{ct_typeid_t, ctevid_t, ctid_t, ctstate_t};
+
+pub(crate) mod ffi;
+
+/*
+ * Simulated processes are given IDs well above those of real processes, so
+ * that they cannot be mistaken for them.
+ */
+const FIRST_PID: pid_t = 1_000_000;
+
+static ENGINE: Mutex<Engine> = Mutex::new(Engine::new());
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Terms>> = const { RefCell::new(None) };
+    static LATEST: Cell<Option<ctid_t>> = const { Cell::new(None) };
+}
+
+/*
+ * The terms of a process contract template, with the defaults documented in
+ * process(5).
+ */
+#[derive(Debug, Clone)]
+struct Terms {
+    cookie: u64,
+    critical: c_uint,
+    informative: c_uint,
+    fatal: c_uint,
+    params: c_uint,
+    transfer: ctid_t,
+    svc_fmri: Option<CString>,
+    svc_aux: Option<CString>,
+}
+
+impl Default for Terms {
+    fn default() -> Terms {
+        Terms {
+            cookie: 0,
+            critical: crate::CT_PR_EV_EMPTY | crate::CT_PR_EV_HWERR,
+            informative: crate::CT_PR_EV_CORE | crate::CT_PR_EV_SIGNAL,
+            fatal: crate::CT_PR_EV_HWERR,
+            params: 0,
+            transfer: 0,
+            svc_fmri: None,
+            svc_aux: None,
+        }
+    }
+}
+
+/*
+ * An event, as queued on a contract and as returned by ct_event_read(); the
+ * handle is a boxed copy, taken when the event is read.
+ */
+#[derive(Debug, Clone)]
+struct MockEvent {
+    ctid: ctid_t,
+    evid: ctevid_t,
+    ty: c_uint,
+    critical: bool,
+    acked: bool,
+    pid: pid_t,
+    ppid: Option<pid_t>,
+    signal: Option<c_int>,
+    sender: Option<(pid_t, ctid_t)>,
+    exitstatus: Option<c_int>,
+    pcorefile: Option<CString>,
+}
+
+impl MockEvent {
+    fn new(ty: c_uint, pid: pid_t) -> MockEvent {
+        MockEvent {
+            ctid: 0,
+            evid: 0,
+            ty,
+            critical: false,
+            acked: false,
+            pid,
+            ppid: None,
+            signal: None,
+            sender: None,
+            exitstatus: None,
+            pcorefile: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MockContract {
+    terms: Terms,
+    state: ctstate_t,
+    holder: id_t,
+    members: Vec<pid_t>,
+    events: Vec<MockEvent>,
+    /*
+     * Whether the contract has been released and so no longer appears in
+     * the contract file system.  Endpoints that are already open can still
+     * read its events and status.
+     */
+    gone: bool,
+}
+
+impl MockContract {
+    /*
+     * Returns the index of the oldest critical event that has not been
+     * acknowledged, which is where a new or reset endpoint starts reading.
+     */
+    fn oldest_pending(&self) -> usize {
+        self.events
+            .iter()
+            .position(|ev| ev.critical && !ev.acked)
+            .unwrap_or(self.events.len())
+    }
+}
+
+#[derive(Debug)]
+enum Object {
+    Template(Terms),
+    Ctl(ctid_t),
+    Status(ctid_t),
+    Events { ctid: ctid_t, next: usize },
+    Bundle { held_only: bool, queue: VecDeque<(ctid_t, ctevid_t)> },
+}
+
+/*
+ * Each open object is given the read end of a pipe as its file descriptor,
+ * and the engine keeps the write end.  Every event queued for an endpoint
+ * writes a byte to the pipe, and reading the event consumes it, so that
+ * endpoints block, and can be polled for readability, as real ones can.
+ *
+ * The engine is not told when a descriptor is closed, so each object also
+ * records the identity of its pipe.  A descriptor whose number is registered
+ * but whose identity differs has been closed, and perhaps reused for some
+ * other file, and the object is then forgotten rather than answering for
+ * that file.
+ */
+#[derive(Debug)]
+struct Open {
+    object: Object,
+    wakeup: OwnedFd,
+    ident: (libc::dev_t, libc::ino_t),
+}
+
+/*
+ * Returns the device and inode numbers of the file open as `fd`.
+ */
+fn ident(fd: RawFd) -> Option<(libc::dev_t, libc::ino_t)> {
+    let mut st = std::mem::MaybeUninit::<libc::stat>::uninit();
+    if unsafe { libc::fstat(fd, st.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let st = unsafe { st.assume_init() };
+    Some((st.st_dev, st.st_ino))
+}
+
+#[derive(Debug)]
+struct Engine {
+    next_ctid: ctid_t,
+    next_evid: ctevid_t,
+    next_pid: pid_t,
+    contracts: BTreeMap<ctid_t, MockContract>,
+    open: BTreeMap<RawFd, Open>,
+}
+
+fn engine() -> MutexGuard<'static, Engine> {
+    /*
+     * A test that panics while holding the lock should not break every
+     * other test in the process.
+     */
+    ENGINE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn holder_pid() -> id_t {
+    std::process::id() as id_t
+}
+
+impl Engine {
+    const fn new() -> Engine {
+        Engine {
+            next_ctid: 1,
+            next_evid: 1,
+            next_pid: FIRST_PID,
+            contracts: BTreeMap::new(),
+            open: BTreeMap::new(),
+        }
+    }
+
+    fn register(&mut self, object: Object) -> Result<OwnedFd, ContractError> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let (rd, wr) = unsafe {
+            (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))
+        };
+
+        for fd in fds {
+            unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+        }
+        unsafe { libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK) };
+
+        self.prune();
+        let ident = ident(fds[0]).ok_or(ContractError::BadDescriptor)?;
+        self.open.insert(rd.as_raw_fd(), Open { object, wakeup: wr, ident });
+        Ok(rd)
+    }
+
+    /*
+     * Forgets the objects whose descriptors have been closed, closing their
+     * write ends.
+     */
+    fn prune(&mut self) {
+        self.open.retain(|&fd, o| ident(fd) == Some(o.ident));
+    }
+
+    /*
+     * Returns the open object for a descriptor, if it is still open.
+     */
+    fn get(&mut self, fd: c_int) -> Option<&mut Open> {
+        if self.open.get(&fd).is_some_and(|o| ident(fd) != Some(o.ident)) {
+            self.open.remove(&fd);
+        }
+        self.open.get_mut(&fd)
+    }
+
+    fn object(&mut self, fd: c_int) -> Option<&mut Object> {
+        self.get(fd).map(|o| &mut o.object)
+    }
+
+    fn contract(&mut self, ctid: ctid_t) -> Option<&mut MockContract> {
+        self.contracts.get_mut(&ctid).filter(|ct| !ct.gone)
+    }
+
+    fn create(&mut self, terms: Terms) -> ctid_t {
+        let ctid = self.next_ctid;
+        self.next_ctid += 1;
+        self.contracts.insert(
+            ctid,
+            MockContract {
+                terms,
+                state: ctstate_t::CTS_OWNED,
+                holder: holder_pid(),
+                members: Vec::new(),
+                events: Vec::new(),
+                gone: false,
+            },
+        );
+        LATEST.set(Some(ctid));
+        ctid
+    }
+
+    fn new_pid(&mut self) -> pid_t {
+        let pid = self.next_pid;
+        self.next_pid += 1;
+        pid
+    }
+
+    /*
+     * Queues an event on a contract, if its terms call for it, and on any
+     * bundles that should see it.
+     */
+    fn post(&mut self, ctid: ctid_t, mut ev: MockEvent) {
+        let Some(ct) = self.contracts.get_mut(&ctid) else {
+            return;
+        };
+        if (ct.terms.critical | ct.terms.informative) & ev.ty == 0 {
+            return;
+        }
+
+        ev.ctid = ctid;
+        ev.evid = self.next_evid;
+        ev.critical = ct.terms.critical & ev.ty != 0;
+        self.next_evid += 1;
+
+        let held = ct.state == ctstate_t::CTS_OWNED;
+        let evid = ev.evid;
+        ct.events.push(ev);
+
+        self.prune();
+        for open in self.open.values_mut() {
+            match &mut open.object {
+                Object::Events { ctid: c, .. } if *c == ctid => {}
+                Object::Bundle { held_only, queue } if held || !*held_only => {
+                    queue.push_back((ctid, evid));
+                }
+                _ => continue,
+            }
+            wake(&open.wakeup, 1);
+        }
+    }
+
+    /*
+     * Removes a member from a contract, as it exits with the given wait(2)
+     * status.
+     */
+    fn exit(&mut self, ctid: ctid_t, pid: pid_t, status: c_int) {
+        self.release(pid as id_t);
+
+        let Some(ct) = self.contracts.get_mut(&ctid) else {
+            return;
+        };
+        ct.members.retain(|&p| p != pid);
+        let empty = ct.members.is_empty();
+
+        let mut ev = MockEvent::new(crate::CT_PR_EV_EXIT, pid);
+        ev.exitstatus = Some(status);
+        self.post(ctid, ev);
+
+        if empty {
+            self.post(ctid, MockEvent::new(crate::CT_PR_EV_EMPTY, pid));
+            let ct = self.contracts.get_mut(&ctid).unwrap();
+            if ct.state != ctstate_t::CTS_OWNED {
+                ct.state = ctstate_t::CTS_DEAD;
+                ct.gone = true;
+            }
+        }
+    }
+
+    /*
+     * Kills every member of a contract with SIGKILL, as happens when a fatal
+     * event occurs or an orphan would be left that is not allowed.
+     */
+    fn kill_all(&mut self, ctid: ctid_t) {
+        let members = self.contracts[&ctid].members.clone();
+        for pid in members {
+            self.exit(ctid, pid, libc::SIGKILL);
+        }
+    }
+
+    /*
+     * Posts an event that kills the process that caused it, and then the
+     * rest of the contract if the event is fatal.
+     */
+    fn die(&mut self, p: &MockProcess, ev: MockEvent, status: c_int) {
+        let fatal = self.contracts[&p.ctid].terms.fatal & ev.ty != 0;
+        self.post(p.ctid, ev);
+        self.exit(p.ctid, p.pid, status);
+        if fatal {
+            self.kill_all(p.ctid);
+        }
+    }
+
+    /*
+     * Abandons every contract held by a simulated process that is exiting,
+     * as the system does for a real one.
+     */
+    fn release(&mut self, holder: id_t) {
+        let held: Vec<_> = self
+            .contracts
+            .iter()
+            .filter(|(_, ct)| {
+                !ct.gone
+                    && ct.state == ctstate_t::CTS_OWNED
+                    && ct.holder == holder
+            })
+            .map(|(&ctid, _)| ctid)
+            .collect();
+        for ctid in held {
+            self.abandon(ctid, holder);
+        }
+    }
+
+    fn abandon(&mut self, ctid: ctid_t, holder: id_t) -> c_int {
+        let Some(ct) = self.contract(ctid) else {
+            return libc::ESRCH;
+        };
+        if ct.state != ctstate_t::CTS_OWNED || ct.holder != holder {
+            return libc::EINVAL;
+        }
+
+        let params = ct.terms.params;
+        ct.holder = 0;
+        if ct.members.is_empty() {
+            ct.state = ctstate_t::CTS_DEAD;
+            ct.gone = true;
+        } else if params & crate::CT_PR_NOORPHAN != 0 {
+            ct.state = ctstate_t::CTS_DEAD;
+            self.kill_all(ctid);
+        } else if params & crate::CT_PR_INHERIT != 0 {
+            ct.state = ctstate_t::CTS_INHERITED;
+        } else {
+            ct.state = ctstate_t::CTS_ORPHAN;
+        }
+        0
+    }
+
+    /*
+     * Returns the next event for an endpoint, if there is one.
+     */
+    fn next_event(&mut self, fd: c_int) -> Option<MockEvent> {
+        self.get(fd)?;
+        let Engine { open, contracts, .. } = self;
+        match &mut open.get_mut(&fd)?.object {
+            Object::Events { ctid, next } => {
+                let ev = contracts.get(ctid)?.events.get(*next)?.clone();
+                *next += 1;
+                Some(ev)
+            }
+            Object::Bundle { queue, .. } => {
+                let (ctid, evid) = queue.pop_front()?;
+                contracts
+                    .get(&ctid)?
+                    .events
+                    .iter()
+                    .find(|ev| ev.evid == evid)
+                    .cloned()
+            }
+            _ => None,
+        }
+    }
+}
+
+fn wake(fd: &OwnedFd, n: usize) {
+    let buf = vec![0u8; n];
+    unsafe { libc::write(fd.as_raw_fd(), buf.as_ptr().cast(), n) };
+}
+
+/*
+ * Opens a file in the contract file system, as the "safe" module would with
+ * open(2).
+ */
+pub(crate) fn open(path: &Path) -> Result<OwnedFd, ContractError> {
+    let rel = path
+        .strip_prefix(ctfs::CTFS_ROOT)
+        .map_err(|_| ContractError::NotFound)?;
+    let parts: Vec<&str> = rel.iter().filter_map(|c| c.to_str()).collect();
+
+    let mut eng = engine();
+    let object = match parts.as_slice() {
+        [ctfs::PROCESS_TYPE, "template"] => Object::Template(Terms::default()),
+        [ctfs::DEVICE_TYPE, "template"] => {
+            return Err(ContractError::NotSupported)
+        }
+        [ctfs::PROCESS_TYPE, "latest"] => {
+            Object::Status(LATEST.get().ok_or(ContractError::NoSuchContract)?)
+        }
+        [ty, bundle @ ("bundle" | "pbundle")]
+            if ct_typeid_t::from_type_name(ty).is_some() =>
+        {
+            Object::Bundle {
+                held_only: *bundle == "pbundle",
+                queue: VecDeque::new(),
+            }
+        }
+        ["all", id, file] => {
+            let ctid = id.parse().map_err(|_| ContractError::NotFound)?;
+            let ct = eng.contract(ctid).ok_or(ContractError::NotFound)?;
+            let next = ct.oldest_pending();
+            let pending = ct.events.len() - next;
+            match *file {
+                "ctl" => Object::Ctl(ctid),
+                "status" => Object::Status(ctid),
+                "events" => {
+                    let fd = eng.register(Object::Events { ctid, next })?;
+                    wake(&eng.open[&fd.as_raw_fd()].wakeup, pending);
+                    return Ok(fd);
+                }
+                _ => return Err(ContractError::NotFound),
+            }
+        }
+        _ => return Err(ContractError::NotFound),
+    };
+
+    eng.register(object)
+}
+
+/*
+ * Returns the IDs of the contracts that appear in /system/contract/all.
+ */
+pub(crate) fn contract_ids() -> Vec<ctid_t> {
+    engine()
+        .contracts
+        .iter()
+        .filter(|(_, ct)| !ct.gone)
+        .map(|(&ctid, _)| ctid)
+        .collect()
+}
+
+/*
+ * Returns the ID of the contract the simulated process `pid` is a member of,
+ * if it is alive.
+ */
+pub(crate) fn contract_of(pid: pid_t) -> Option<ctid_t> {
+    engine()
+        .contracts
+        .iter()
+        .find(|(_, ct)| ct.members.contains(&pid))
+        .map(|(&ctid, _)| ctid)
+}
+
+/// Creates a simulated process in a new process contract, as fork(2) would,
+/// using the template that is active on the calling thread.  The new
+/// contract becomes the latest one for the thread, so that
+/// [`crate::safe::contract_latest`] returns it.
+///
+/// This fails with [`ContractError::InvalidArgument`] if no template is
+/// active.
+pub fn spawn() -> Result<MockProcess, ContractError> {
+    let terms = ACTIVE
+        .with_borrow(|t| t.clone())
+        .ok_or(ContractError::InvalidArgument)?;
+
+    let mut eng = engine();
+    let ctid = eng.create(terms);
+    let pid = eng.new_pid();
+    eng.contracts.get_mut(&ctid).unwrap().members.push(pid);
+    Ok(MockProcess { pid, ctid })
+}
+
+/// A simulated process, the member of a simulated process contract; see
+/// [`spawn`].
+///
+/// The methods that end the process consume it, but the process may also be
+/// killed because of something that happens to another member of its
+/// contract.  Methods called on a process that has already been killed fail
+/// with [`ContractError::NoSuchContract`], which carries ESRCH as kill(2)
+/// would.
+#[derive(Debug)]
+pub struct MockProcess {
+    pid: pid_t,
+    ctid: ctid_t,
+}
+
+impl MockProcess {
+    pub fn pid(&self) -> pid_t {
+        self.pid
+    }
+
+    /// Returns the ID of the contract the process is a member of.
+    pub fn ctid(&self) -> ContractId {
+        ContractId(self.ctid)
+    }
+
+    fn alive(&self, eng: &Engine) -> Result<(), ContractError> {
+        match eng.contracts.get(&self.ctid) {
+            Some(ct) if ct.members.contains(&self.pid) => Ok(()),
+            _ => Err(ContractError::NoSuchContract),
+        }
+    }
+
+    /// Forks a child, which joins the same contract, causing a
+    /// CT_PR_EV_FORK event.
+    pub fn fork(&self) -> Result<MockProcess, ContractError> {
+        let mut eng = engine();
+        self.alive(&eng)?;
+
+        let pid = eng.new_pid();
+        eng.contracts.get_mut(&self.ctid).unwrap().members.push(pid);
+        let mut ev = MockEvent::new(crate::CT_PR_EV_FORK, pid);
+        ev.ppid = Some(self.pid);
+        eng.post(self.ctid, ev);
+        Ok(MockProcess { pid, ctid: self.ctid })
+    }
+
+    /// Adopts the orphaned or inherited contract `ctid`, as
+    /// ct_ctl_adopt(3CONTRACT) would, so that it is held by this process
+    /// rather than by the caller.  When this process exits, the contracts
+    /// it holds are abandoned.
+    pub fn adopt(&self, ctid: ContractId) -> Result<(), ContractError> {
+        let mut eng = engine();
+        self.alive(&eng)?;
+
+        let ct =
+            eng.contract(ctid.as_raw()).ok_or(ContractError::NoSuchContract)?;
+        match ct.state {
+            ctstate_t::CTS_ORPHAN | ctstate_t::CTS_INHERITED => {
+                ct.state = ctstate_t::CTS_OWNED;
+                ct.holder = self.pid as id_t;
+                Ok(())
+            }
+            ctstate_t::CTS_OWNED => Err(ContractError::Busy),
+            _ => Err(ContractError::NotInheritable),
+        }
+    }
+
+    /// Exits with the given code, causing a CT_PR_EV_EXIT event, and a
+    /// CT_PR_EV_EMPTY event if this was the last member of the contract.
+    pub fn exit(self, code: c_int) -> Result<(), ContractError> {
+        let mut eng = engine();
+        self.alive(&eng)?;
+        eng.exit(self.ctid, self.pid, (code & 0xff) << 8);
+        Ok(())
+    }
+
+    /// Dies of signal `sig` sent by a process outside the contract, causing
+    /// a CT_PR_EV_SIGNAL event and then an exit.  `sender` gives the ID of
+    /// the sending process and of its contract, if they should be reported.
+    pub fn kill(
+        self,
+        sig: c_int,
+        sender: Option<(pid_t, ContractId)>,
+    ) -> Result<(), ContractError> {
+        let mut eng = engine();
+        self.alive(&eng)?;
+
+        let mut ev = MockEvent::new(crate::CT_PR_EV_SIGNAL, self.pid);
+        ev.signal = Some(sig);
+        ev.sender = sender.map(|(pid, ctid)| (pid, ctid.as_raw()));
+        eng.die(&self, ev, sig);
+        Ok(())
+    }
+
+    /// Dies of signal `sig`, dumping core to a file named `core.<pid>`,
+    /// causing a CT_PR_EV_CORE event and then an exit.
+    pub fn core(self, sig: c_int) -> Result<(), ContractError> {
+        let mut eng = engine();
+        self.alive(&eng)?;
+
+        let mut ev = MockEvent::new(crate::CT_PR_EV_CORE, self.pid);
+        ev.pcorefile = CString::new(format!("core.{}", self.pid)).ok();
+        eng.die(&self, ev, sig | 0x80);
+        Ok(())
+    }
+
+    /// Is killed by an uncorrectable hardware error, causing a
+    /// CT_PR_EV_HWERR event and then an exit.
+    pub fn hwerr(self) -> Result<(), ContractError> {
+        let mut eng = engine();
+        self.alive(&eng)?;
+
+        let ev = MockEvent::new(crate::CT_PR_EV_HWERR, self.pid);
+        eng.die(&self, ev, libc::SIGKILL);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_descriptor_is_forgotten() {
+        drop(open(Path::new(ctfs::PROCESS_TEMPLATE)).unwrap());
+
+        /*
+         * Descriptors are allocated lowest first, so a file opened now most
+         * likely gets the number the template had; in any case, it must not
+         * be taken for the template.
+         */
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let rv = unsafe { ffi::ct_tmpl_activate(file.as_raw_fd()) };
+        assert_eq!(rv, libc::EBADF);
+    }
+
+    #[test]
+    fn holder_exit_abandons_contracts() {
+        let tmpl = crate::safe::ProcessTemplateBuilder::new().build().unwrap();
+        let _active = tmpl.activate_guard().unwrap();
+        let holder = spawn().unwrap();
+        let orphan = spawn().unwrap();
+        let ctid = orphan.ctid();
+
+        let ctl = open(&ctfs::ctl_path(ctid.as_raw())).unwrap();
+        assert_eq!(unsafe { ffi::ct_ctl_abandon(ctl.as_raw_fd()) }, 0);
+        holder.adopt(ctid).unwrap();
+        assert!(matches!(holder.adopt(ctid), Err(ContractError::Busy)));
+
+        /*
+         * The caller no longer holds the contract, and so cannot abandon it;
+         * once its holder exits, it is an orphan again.
+         */
+        assert_eq!(
+            unsafe { ffi::ct_ctl_abandon(ctl.as_raw_fd()) },
+            libc::EINVAL
+        );
+        let state = |eng: &Engine| eng.contracts[&ctid.as_raw()].state;
+        assert_eq!(state(&engine()), ctstate_t::CTS_OWNED);
+        holder.exit(0).unwrap();
+        assert_eq!(state(&engine()), ctstate_t::CTS_ORPHAN);
+        orphan.exit(0).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use super::{Contract, ContractError, Detail, EventEndpoint, Status};
+use crate::ctstate_t;
+use crate::ids::ContractId;
+
+/// Recovers contracts held by a previous instance of the calling program,
+/// e.g., a restarter that records the IDs of its contracts so that it can
+/// pick them up again after it is restarted.
+///
+/// When the previous instance exited, its contracts were inherited by its
+/// regent, if it had one, or orphaned.  [`AdoptionManager::recover`] adopts
+/// each such contract, and opens its event endpoint afresh.  Contracts the
+/// caller already holds are recovered without being adopted again.
+#[derive(Debug, Clone, Default)]
+pub struct AdoptionManager {
+    ctids: Vec<ContractId>,
+}
+
+/// A contract recovered by an [`AdoptionManager`].
+#[derive(Debug)]
+pub struct Recovered {
+    pub contract: Contract,
+    pub events: EventEndpoint,
+    /// Whether the contract was adopted, rather than already being held by
+    /// the caller.
+    pub adopted: bool,
+}
+
+/// The outcome of [`AdoptionManager::recover`].
+#[derive(Debug, Default)]
+pub struct Recovery {
+    pub recovered: Vec<Recovered>,
+    /// The contracts that could not be recovered, and why.  A contract held
+    /// by some other process is reported as [`ContractError::Busy`], and a
+    /// dead contract as [`ContractError::NoSuchContract`].
+    pub failed: Vec<(ContractId, ContractError)>,
+}
+
+impl AdoptionManager {
+    pub fn new() -> AdoptionManager {
+        Default::default()
+    }
+
+    /// Adds a contract to be recovered.
+    pub fn add(&mut self, ctid: ContractId) -> &mut AdoptionManager {
+        if !self.ctids.contains(&ctid) {
+            self.ctids.push(ctid);
+        }
+        self
+    }
+
+    pub fn ctids(&self) -> &[ContractId] {
+        &self.ctids
+    }
+
+    /// Attempts to recover each contract in turn.  A failure to recover one
+    /// contract does not prevent the others from being recovered.
+    pub fn recover(&self) -> Recovery {
+        let mut out = Recovery::default();
+
+        for &ctid in &self.ctids {
+            match recover_one(ctid) {
+                Ok(r) => out.recovered.push(r),
+                Err(e) => out.failed.push((ctid, e)),
+            }
+        }
+
+        out
+    }
+}
+
+impl FromIterator<ContractId> for AdoptionManager {
+    fn from_iter<I: IntoIterator<Item = ContractId>>(
+        iter: I,
+    ) -> AdoptionManager {
+        let mut mgr = AdoptionManager::new();
+        for ctid in iter {
+            mgr.add(ctid);
+        }
+        mgr
+    }
+}
+
+fn recover_one(ctid: ContractId) -> Result<Recovered, ContractError> {
+    /*
+     * A dead contract is removed from the contract file system once nothing
+     * refers to it any more, and is then reported in the same way as one
+     * that is still there.
+     */
+    let gone = |e| match e {
+        ContractError::NotFound => ContractError::NoSuchContract,
+        e => e,
+    };
+    let st = Status::read_ctid(ctid, Detail::Common).map_err(gone)?;
+    let contract = Contract::open(ctid).map_err(gone)?;
+
+    let adopted = match st.state() {
+        ctstate_t::CTS_INHERITED | ctstate_t::CTS_ORPHAN => {
+            contract.adopt()?;
+            true
+        }
+        ctstate_t::CTS_OWNED => {
+            if st.holder() != std::process::id() as libc::id_t {
+                return Err(ContractError::Busy);
+            }
+            false
+        }
+        _ => return Err(ContractError::NoSuchContract),
+    };
+
+    /*
+     * The endpoint is opened after adoption, so that it sees the events that
+     * are sent to the new holder.
+     */
+    let events = contract.events()?;
+
+    Ok(Recovered { contract, events, adopted })
+}
@@ -0,0 +1,81 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+use super::{ContractError, Event, EventEndpoint};
+use crate::ids::ContractId;
+
+/// An event endpoint registered with the tokio reactor.  Events can be read
+/// with [`AsyncEventEndpoint::read`], or by using the endpoint as a
+/// [`Stream`].
+#[derive(Debug)]
+pub struct AsyncEventEndpoint {
+    inner: AsyncFd<EventEndpoint>,
+}
+
+impl AsyncEventEndpoint {
+    /// Registers `ep` with the current tokio reactor, putting it in
+    /// non-blocking mode.  This must be called from within a tokio runtime.
+    pub fn new(ep: EventEndpoint) -> Result<AsyncEventEndpoint, ContractError> {
+        ep.set_nonblocking(true)?;
+        let inner = AsyncFd::with_interest(ep, Interest::READABLE)?;
+        Ok(AsyncEventEndpoint { inner })
+    }
+
+    /// Opens the event endpoint of the contract with ID `ctid`.
+    pub fn for_contract(
+        ctid: ContractId,
+    ) -> Result<AsyncEventEndpoint, ContractError> {
+        AsyncEventEndpoint::new(EventEndpoint::for_contract(ctid)?)
+    }
+
+    pub fn get_ref(&self) -> &EventEndpoint {
+        self.inner.get_ref()
+    }
+
+    /// Returns the wrapped endpoint, deregistering it from the reactor.  The
+    /// endpoint is left in non-blocking mode.
+    pub fn into_inner(self) -> EventEndpoint {
+        self.inner.into_inner()
+    }
+
+    /// Waits for and reads the next event from the endpoint.
+    pub async fn read(&self) -> Result<Event, ContractError> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+            match guard.get_inner().read() {
+                Err(ContractError::WouldBlock) => guard.clear_ready(),
+                res => return res,
+            }
+        }
+    }
+
+    fn poll_read(&self, cx: &mut Context<'_>) -> Poll<io::Result<Event>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_read_ready(cx))?;
+            match guard.get_inner().read() {
+                Err(ContractError::WouldBlock) => guard.clear_ready(),
+                res => return Poll::Ready(res.map_err(io::Error::from)),
+            }
+        }
+    }
+}
+
+impl Stream for AsyncEventEndpoint {
+    type Item = io::Result<Event>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<Event>>> {
+        self.poll_read(cx).map(Some)
+    }
+}
@@ -0,0 +1,219 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::io;
+use std::os::fd::AsRawFd;
+
+use super::process::latest_id;
+use super::{contract_latest, stats, Contract, ContractError};
+use super::{ProcessTemplateBuilder, Template};
+use crate as sys;
+use crate::ids::ContractId;
+
+/// Extends process builders, such as [`std::process::Command`], to spawn
+/// children in a new process contract.
+pub trait ContractCommandExt {
+    type Child;
+
+    /// Spawns the command in a new process contract created from `tmpl`,
+    /// returning the child and the control endpoint of its contract.
+    ///
+    /// The template is activated on the calling thread only for the
+    /// duration of the spawn, and is cleared again before this returns,
+    /// whether or not the spawn succeeded (or panicked).
+    fn spawn_in_contract(
+        &mut self,
+        tmpl: &Template,
+    ) -> Result<(Self::Child, Contract), ContractError>;
+}
+
+/// Spawns `command` in a new process contract with the terms in `config`,
+/// returning the child and the control endpoint of its contract.  This is
+/// what ctrun(1) does: the contract lets the child and all of its
+/// descendants be treated as a unit, e.g., by waiting for CT_PR_EV_EMPTY or
+/// signalling every member of the contract at once.
+pub fn run_in_contract(
+    mut command: std::process::Command,
+    config: &ProcessTemplateBuilder,
+) -> Result<(std::process::Child, Contract), ContractError> {
+    command.spawn_in_contract(&config.build()?)
+}
+
+impl ContractCommandExt for std::process::Command {
+    type Child = std::process::Child;
+
+    fn spawn_in_contract(
+        &mut self,
+        tmpl: &Template,
+    ) -> Result<(std::process::Child, Contract), ContractError> {
+        spawn_with(
+            tmpl,
+            || self.spawn(),
+            |child| {
+                let _ = child.kill();
+                let _ = child.wait();
+            },
+        )
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl ContractCommandExt for tokio::process::Command {
+    type Child = tokio::process::Child;
+
+    fn spawn_in_contract(
+        &mut self,
+        tmpl: &Template,
+    ) -> Result<(tokio::process::Child, Contract), ContractError> {
+        /*
+         * The runtime reaps killed children that are dropped without being
+         * waited for.
+         */
+        spawn_with(
+            tmpl,
+            || self.spawn(),
+            |child| {
+                let _ = child.start_kill();
+            },
+        )
+    }
+}
+
+fn spawn_with<C>(
+    tmpl: &Template,
+    spawn: impl FnOnce() -> io::Result<C>,
+    kill: impl FnOnce(&mut C),
+) -> Result<(C, Contract), ContractError> {
+    /*
+     * The contract is created when the child is forked, so the template
+     * must be active in the parent; it would be too late to activate it in
+     * a pre_exec() hook in the child.
+     */
+    let before = latest_id().ok();
+    let active = tmpl.activate_guard()?;
+    let res = spawn();
+    let clear = active.clear();
+
+    /*
+     * The child may have been forked, creating its contract, even if the
+     * spawn failed because exec did.  Nobody else knows of the contract
+     * until it is returned, so it is abandoned rather than leaked on any
+     * of the failure paths below.
+     */
+    let created = latest_id().ok().filter(|&ctid| Some(ctid) != before);
+    let abandon = || {
+        if let Some(ctid) = created {
+            abandon_new(ctid);
+        }
+    };
+
+    let mut child = match res {
+        Ok(child) => child,
+        Err(e) => {
+            abandon();
+            return Err(e.into());
+        }
+    };
+    match clear.and_then(|_| contract_latest()) {
+        Ok((ct, _)) => {
+            stats::contract_held();
+            Ok((child, ct))
+        }
+        Err(e) => {
+            /*
+             * Without its contract the caller cannot manage the child
+             * properly, so don't leave it running.
+             */
+            kill(&mut child);
+            abandon();
+            Err(e)
+        }
+    }
+}
+
+/*
+ * Abandons a contract created by a failed spawn.  The contract was never
+ * counted as held, so it is abandoned directly rather than with
+ * Contract::abandon().
+ */
+fn abandon_new(ctid: ContractId) {
+    if let Ok(ct) = Contract::open(ctid) {
+        unsafe { sys::ct_ctl_abandon(ct.as_raw_fd()) };
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use crate::safe::all_contracts;
+
+    fn listed(ctid: ContractId) -> bool {
+        all_contracts().unwrap().any(|res| res.unwrap().0 == ctid)
+    }
+
+    #[test]
+    fn failed_exec_abandons_contract() {
+        let tmpl = ProcessTemplateBuilder::new().build().unwrap();
+        let mut created = None;
+
+        let res = spawn_with(
+            &tmpl,
+            || {
+                /*
+                 * As if the child were forked into its new contract, and
+                 * then exited after failing to exec.
+                 */
+                let p = mock::spawn().unwrap();
+                created = Some(p.ctid());
+                p.exit(127).unwrap();
+                Err::<(), _>(io::Error::from_raw_os_error(libc::ENOENT))
+            },
+            |_| (),
+        );
+
+        assert!(matches!(res, Err(ContractError::NotFound)));
+        assert!(!listed(created.unwrap()));
+    }
+
+    #[test]
+    fn failed_clear_abandons_contract() {
+        let tmpl = ProcessTemplateBuilder::new().build().unwrap();
+        let mut created = None;
+
+        let res = spawn_with(
+            &tmpl,
+            || {
+                let p = mock::spawn().unwrap();
+                created = Some(p.ctid());
+
+                /*
+                 * Replace the template's descriptor, so that clearing the
+                 * active template fails.
+                 */
+                let null = std::fs::File::open("/dev/null").unwrap();
+                unsafe { libc::dup2(null.as_raw_fd(), tmpl.as_raw_fd()) };
+                Ok(Some(p))
+            },
+            |p| p.take().unwrap().kill(libc::SIGKILL, None).unwrap(),
+        );
+
+        assert!(matches!(res, Err(ContractError::BadDescriptor)));
+        assert!(!listed(created.unwrap()));
+    }
+
+    #[test]
+    fn missing_binary_leaves_no_contract() {
+        let tmpl = ProcessTemplateBuilder::new().build().unwrap();
+        let before = latest_id().ok();
+
+        let res = std::process::Command::new("/nonexistent/ctrun-test")
+            .spawn_in_contract(&tmpl);
+
+        assert!(matches!(res, Err(ContractError::NotFound)));
+        if let Ok(ctid) = latest_id() {
+            assert!(Some(ctid) == before || !listed(ctid));
+        }
+    }
+}
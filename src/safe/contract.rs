@@ -0,0 +1,182 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::fmt;
+use std::ops::Deref;
+use std::os::fd::{AsRawFd, OwnedFd};
+
+use super::{check, impl_as_fd, open_write, stats, ContractError};
+use super::{Detail, EventEndpoint, Status, Template};
+use crate as sys;
+use crate::ctfs;
+use crate::ids::{ContractId, EventId};
+
+/// A contract, accessed through its control endpoint; see
+/// ct_ctl_adopt(3CONTRACT).
+#[derive(Debug)]
+pub struct Contract {
+    ctid: ContractId,
+    ctl: OwnedFd,
+}
+
+impl Contract {
+    /// Opens the control endpoint of the contract with ID `ctid`.
+    pub fn open(ctid: ContractId) -> Result<Contract, ContractError> {
+        let ctl = open_write(&ctfs::ctl_path(ctid.as_raw()))?;
+        Ok(Contract { ctid, ctl })
+    }
+
+    /// Wraps an already open control endpoint for the contract with ID
+    /// `ctid`.
+    pub fn from_ctl(ctid: ContractId, ctl: OwnedFd) -> Contract {
+        Contract { ctid, ctl }
+    }
+
+    pub fn id(&self) -> ContractId {
+        self.ctid
+    }
+
+    /// Reads the status of this contract at the requested level of detail.
+    pub fn status(&self, detail: Detail) -> Result<Status, ContractError> {
+        Status::read_ctid(self.ctid, detail)
+    }
+
+    /// Opens the event endpoint of this contract.
+    pub fn events(&self) -> Result<EventEndpoint, ContractError> {
+        EventEndpoint::for_contract(self.ctid)
+    }
+
+    /// Opens the contract with ID `ctid` and adopts it, making the caller
+    /// its holder.  See [`Contract::adopt`].
+    pub fn adopt_id(ctid: ContractId) -> Result<Contract, ContractError> {
+        let ct = Contract::open(ctid)?;
+        ct.adopt()?;
+        Ok(ct)
+    }
+
+    /// Adopts this contract, which must be inherited by the caller's process
+    /// contract or orphaned.  Otherwise, this fails with
+    /// [`ContractError::NotInheritable`].
+    pub fn adopt(&self) -> Result<(), ContractError> {
+        match check(unsafe { sys::ct_ctl_adopt(self.ctl.as_raw_fd()) }) {
+            Ok(()) => {
+                stats::contract_held();
+                Ok(())
+            }
+            Err(ContractError::AccessDenied) => {
+                Err(ContractError::NotInheritable)
+            }
+            res => res,
+        }
+    }
+
+    pub fn abandon(&self) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_ctl_abandon(self.ctl.as_raw_fd()) })?;
+        stats::contract_released();
+        Ok(())
+    }
+
+    /// Returns a guard that abandons this contract when dropped, so that it
+    /// is not left to be inherited if the holder gives up on it early, e.g.,
+    /// by returning an error or panicking.  Use [`AbandonGuard::leak`] to
+    /// keep the contract after all.
+    pub fn abandon_on_drop(self) -> AbandonGuard {
+        AbandonGuard { ct: Some(self), on_error: None }
+    }
+
+    pub fn ack(&self, evid: EventId) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_ctl_ack(self.ctl.as_raw_fd(), evid.as_raw()) })?;
+        stats::response_sent("ack");
+        Ok(())
+    }
+
+    pub fn nack(&self, evid: EventId) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_ctl_nack(self.ctl.as_raw_fd(), evid.as_raw())
+        })?;
+        stats::response_sent("nack");
+        Ok(())
+    }
+
+    pub fn qack(&self, evid: EventId) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_ctl_qack(self.ctl.as_raw_fd(), evid.as_raw())
+        })?;
+        stats::response_sent("qack");
+        Ok(())
+    }
+
+    /// Requests that `template` be used to create the contract that
+    /// replaces this one when the negotiation for event `evid` completes.
+    pub fn newct(
+        &self,
+        evid: EventId,
+        template: &Template,
+    ) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_ctl_newct(
+                self.ctl.as_raw_fd(),
+                evid.as_raw(),
+                template.as_raw_fd(),
+            )
+        })
+    }
+}
+
+impl_as_fd!(Contract, ctl);
+
+type AbandonErrorFn = Box<dyn FnMut(ContractId, ContractError) + Send>;
+
+/// A contract that is abandoned when dropped; see
+/// [`Contract::abandon_on_drop`].
+pub struct AbandonGuard {
+    ct: Option<Contract>,
+    on_error: Option<AbandonErrorFn>,
+}
+
+impl AbandonGuard {
+    /// Sets a function to be called with the contract ID and the error if
+    /// abandoning the contract fails when the guard is dropped.  By default
+    /// such errors are ignored.
+    pub fn on_error<F>(mut self, f: F) -> AbandonGuard
+    where
+        F: FnMut(ContractId, ContractError) + Send + 'static,
+    {
+        self.on_error = Some(Box::new(f));
+        self
+    }
+
+    /// Disarms the guard, returning the contract without abandoning it.
+    pub fn leak(mut self) -> Contract {
+        self.ct.take().unwrap()
+    }
+}
+
+impl Deref for AbandonGuard {
+    type Target = Contract;
+
+    fn deref(&self) -> &Contract {
+        self.ct.as_ref().unwrap()
+    }
+}
+
+impl fmt::Debug for AbandonGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AbandonGuard").field("ct", &self.ct).finish()
+    }
+}
+
+impl Drop for AbandonGuard {
+    fn drop(&mut self) {
+        let Some(ct) = self.ct.take() else {
+            return;
+        };
+
+        if let Err(e) = ct.abandon() {
+            if let Some(f) = self.on_error.as_mut() {
+                f(ct.id(), e);
+            }
+        }
+    }
+}
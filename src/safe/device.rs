@@ -0,0 +1,511 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::ffi::{CStr, CString};
+use std::os::fd::AsRawFd;
+use std::os::raw::{c_char, c_uint};
+
+use super::status::status_str;
+use super::{check, cstring, ContractError, CteFlags, Detail, DevEventSet};
+use super::{Event, Status, Template};
+use crate as sys;
+use crate::ctfs;
+use crate::ids::{ContractId, EventId};
+
+/*
+ * Minor node paths are /devices paths, and must fit in MAXPATHLEN bytes
+ * including the terminating NUL.
+ */
+const DEVICES_PREFIX: &str = "/devices/";
+const MAXPATHLEN: usize = 1024;
+
+/// A device state, as used in the acceptable state set of a device contract
+/// and reported by ct_dev_status_get_dev_state(3CONTRACT).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceState {
+    Online,
+    Degraded,
+    Offline,
+}
+
+impl DeviceState {
+    pub const fn as_raw(self) -> c_uint {
+        match self {
+            DeviceState::Online => sys::CT_DEV_EV_ONLINE,
+            DeviceState::Degraded => sys::CT_DEV_EV_DEGRADED,
+            DeviceState::Offline => sys::CT_DEV_EV_OFFLINE,
+        }
+    }
+
+    pub fn from_raw(state: c_uint) -> Option<DeviceState> {
+        match state {
+            sys::CT_DEV_EV_ONLINE => Some(DeviceState::Online),
+            sys::CT_DEV_EV_DEGRADED => Some(DeviceState::Degraded),
+            sys::CT_DEV_EV_OFFLINE => Some(DeviceState::Offline),
+            _ => None,
+        }
+    }
+}
+
+/// A set of acceptable device states for a device contract; see
+/// ct_dev_tmpl_set_aset(3CONTRACT).  A set only ever contains known states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "c_uint", into = "c_uint")
+)]
+pub struct DeviceStateSet(c_uint);
+
+impl DeviceStateSet {
+    pub const ONLINE: DeviceStateSet = DeviceStateSet(sys::CT_DEV_EV_ONLINE);
+    pub const DEGRADED: DeviceStateSet =
+        DeviceStateSet(sys::CT_DEV_EV_DEGRADED);
+    pub const OFFLINE: DeviceStateSet = DeviceStateSet(sys::CT_DEV_EV_OFFLINE);
+
+    /*
+     * Common aggregates: a device that may keep running in a degraded
+     * state, and one that may be in any state at all.
+     */
+    pub const USABLE: DeviceStateSet =
+        DeviceStateSet(sys::CT_DEV_EV_ONLINE | sys::CT_DEV_EV_DEGRADED);
+    pub const ALL: DeviceStateSet = DeviceStateSet(sys::CT_DEV_ALLEVENT);
+
+    pub const fn empty() -> DeviceStateSet {
+        DeviceStateSet(0)
+    }
+
+    /// Returns this set with `state` added to it.
+    pub const fn with(self, state: DeviceState) -> DeviceStateSet {
+        DeviceStateSet(self.0 | state.as_raw())
+    }
+
+    pub const fn contains(self, state: DeviceState) -> bool {
+        self.0 & state.as_raw() != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn as_raw(self) -> c_uint {
+        self.0
+    }
+
+    /// Converts a raw set of CT_DEV_EV_* values, which must not contain any
+    /// unknown bits.
+    pub fn from_raw(aset: c_uint) -> Result<DeviceStateSet, ContractError> {
+        if aset & !sys::CT_DEV_ALLEVENT != 0 {
+            return Err(ContractError::InvalidArgument);
+        }
+
+        Ok(DeviceStateSet(aset))
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = DeviceState> {
+        [DeviceState::Online, DeviceState::Degraded, DeviceState::Offline]
+            .into_iter()
+            .filter(move |s| self.contains(*s))
+    }
+}
+
+impl From<DeviceState> for DeviceStateSet {
+    fn from(state: DeviceState) -> DeviceStateSet {
+        DeviceStateSet::empty().with(state)
+    }
+}
+
+impl TryFrom<c_uint> for DeviceStateSet {
+    type Error = ContractError;
+
+    fn try_from(aset: c_uint) -> Result<DeviceStateSet, ContractError> {
+        DeviceStateSet::from_raw(aset)
+    }
+}
+
+impl From<DeviceStateSet> for c_uint {
+    fn from(aset: DeviceStateSet) -> c_uint {
+        aset.as_raw()
+    }
+}
+
+impl FromIterator<DeviceState> for DeviceStateSet {
+    fn from_iter<I: IntoIterator<Item = DeviceState>>(
+        iter: I,
+    ) -> DeviceStateSet {
+        iter.into_iter().fold(DeviceStateSet::empty(), DeviceStateSet::with)
+    }
+}
+
+impl Status {
+    /// Returns the current state of the device covered by a device
+    /// contract.  This requires a status read with [`Detail::Fixed`] or
+    /// more detail.  An unrecognised state is reported as
+    /// [`ContractError::NotSupported`].
+    pub fn dev_state(&self) -> Result<DeviceState, ContractError> {
+        self.require(Detail::Fixed)?;
+        let mut state = 0;
+        check(unsafe {
+            sys::ct_dev_status_get_dev_state(self.as_ptr(), &mut state)
+        })?;
+        DeviceState::from_raw(state).ok_or(ContractError::NotSupported)
+    }
+
+    /// Returns the acceptable states of a device contract.  This and the
+    /// other device accessors require at least [`Detail::Fixed`].
+    pub fn dev_aset(&self) -> Result<DeviceStateSet, ContractError> {
+        self.require(Detail::Fixed)?;
+        let mut aset = 0;
+        check(unsafe {
+            sys::ct_dev_status_get_aset(self.as_ptr(), &mut aset)
+        })?;
+        DeviceStateSet::from_raw(aset)
+    }
+
+    /// Returns the /devices path of the minor node the contract covers.
+    pub fn dev_minor(&self) -> Result<&CStr, ContractError> {
+        self.require(Detail::Fixed)?;
+        status_str(|p| unsafe {
+            sys::ct_dev_status_get_minor(self.as_ptr(), p)
+        })
+    }
+
+    pub fn dev_noneg(&self) -> Result<bool, ContractError> {
+        self.require(Detail::Fixed)?;
+        let mut noneg = 0;
+        check(unsafe {
+            sys::ct_dev_status_get_noneg(self.as_ptr(), &mut noneg)
+        })?;
+        Ok(noneg != 0)
+    }
+}
+
+/// What a device contract event reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceEventKind {
+    /// The device has moved, or is proposing to move, to the given state.
+    Transition(DeviceState),
+    /// The negotiation started by event `nevid` has ended.  If the contract
+    /// was replaced as part of the negotiation, `newct` is the ID of the new
+    /// contract; otherwise it is the ID of the contract itself.
+    NegEnd { nevid: EventId, newct: ContractId },
+}
+
+/// A decoded device contract event; see devices(5).
+///
+/// Device events themselves only carry the state transition.  The minor node
+/// path and the noneg term are read from the status of the contract when the
+/// event is decoded, and are absent if that status could not be read, e.g.,
+/// because the contract has since been abandoned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceEvent {
+    pub ctid: ContractId,
+    pub evid: EventId,
+    pub flags: CteFlags,
+    pub kind: DeviceEventKind,
+    pub minor: Option<String>,
+    pub noneg: Option<bool>,
+}
+
+impl DeviceEvent {
+    /// Decodes an event read from the endpoint of a device contract.  Events
+    /// of an unknown type are reported as [`ContractError::NotSupported`].
+    pub fn decode(ev: &Event) -> Result<DeviceEvent, ContractError> {
+        let kind = match ev.event_type() {
+            sys::CT_EV_NEGEND => DeviceEventKind::NegEnd {
+                nevid: ev.nevid()?,
+                newct: ev.newct()?,
+            },
+            t => DeviceEventKind::Transition(
+                DeviceState::from_raw(t).ok_or(ContractError::NotSupported)?,
+            ),
+        };
+
+        let status = Status::read_ctid(ev.ctid(), Detail::Fixed).ok();
+        let minor = status
+            .as_ref()
+            .and_then(|st| st.dev_minor().ok())
+            .map(|m| m.to_string_lossy().into_owned());
+        let noneg = status.as_ref().and_then(|st| st.dev_noneg().ok());
+
+        Ok(DeviceEvent {
+            ctid: ev.ctid(),
+            evid: ev.evid(),
+            flags: ev.flags(),
+            kind,
+            minor,
+            noneg,
+        })
+    }
+
+    /// Returns true if the event is a negotiation that must be answered with
+    /// an acknowledgement before the transition proceeds.
+    pub fn is_negotiation(&self) -> bool {
+        self.flags.contains(CteFlags::NEG)
+    }
+
+    /// Returns the state the device is moving to, unless this event ends a
+    /// negotiation.
+    pub fn state(&self) -> Option<DeviceState> {
+        match self.kind {
+            DeviceEventKind::Transition(state) => Some(state),
+            DeviceEventKind::NegEnd { .. } => None,
+        }
+    }
+}
+
+/*
+ * Device contract template terms; see ct_dev_tmpl_set_aset(3CONTRACT).
+ */
+impl Template {
+    /// Sets the acceptable states for the device.  The set must not be
+    /// empty.
+    pub fn set_aset(&self, aset: DeviceStateSet) -> Result<(), ContractError> {
+        if aset.is_empty() {
+            return Err(ContractError::InvalidArgument);
+        }
+
+        check(unsafe {
+            sys::ct_dev_tmpl_set_aset(self.as_raw_fd(), aset.as_raw())
+        })
+    }
+
+    pub fn aset(&self) -> Result<DeviceStateSet, ContractError> {
+        let mut aset = 0;
+        check(unsafe {
+            sys::ct_dev_tmpl_get_aset(self.as_raw_fd(), &mut aset)
+        })?;
+        DeviceStateSet::from_raw(aset)
+    }
+
+    pub fn set_minor(&self, minor: &CStr) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_dev_tmpl_set_minor(self.as_raw_fd(), minor.as_ptr())
+        })
+    }
+
+    pub fn minor(&self) -> Result<String, ContractError> {
+        let mut buf = vec![0u8; MAXPATHLEN];
+
+        loop {
+            let mut len = buf.len();
+            let rv = unsafe {
+                sys::ct_dev_tmpl_get_minor(
+                    self.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut c_char,
+                    &mut len,
+                )
+            };
+
+            /*
+             * If the buffer was too small, the required size has been
+             * stored in "len" and we can try again.
+             */
+            if rv == libc::EOVERFLOW && len > buf.len() {
+                buf.resize(len, 0);
+                continue;
+            }
+            check(rv)?;
+
+            let minor = CStr::from_bytes_until_nul(&buf)
+                .map_err(|_| ContractError::Overflow)?;
+            return Ok(minor.to_string_lossy().into_owned());
+        }
+    }
+
+    pub fn set_noneg(&self) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_dev_tmpl_set_noneg(self.as_raw_fd()) })
+    }
+
+    pub fn clear_noneg(&self) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_dev_tmpl_clear_noneg(self.as_raw_fd()) })
+    }
+
+    pub fn noneg(&self) -> Result<bool, ContractError> {
+        let mut noneg = 0;
+        check(unsafe {
+            sys::ct_dev_tmpl_get_noneg(self.as_raw_fd(), &mut noneg)
+        })?;
+        Ok(noneg != 0)
+    }
+}
+
+/// Configures a device contract template; see contract(5).
+#[derive(Debug, Clone, Default)]
+pub struct DeviceTemplateBuilder {
+    aset: Option<DeviceStateSet>,
+    minor: Option<String>,
+    noneg: Option<bool>,
+    critical: Option<DevEventSet>,
+    informative: Option<DevEventSet>,
+    cookie: Option<u64>,
+}
+
+impl DeviceTemplateBuilder {
+    pub fn new() -> DeviceTemplateBuilder {
+        Default::default()
+    }
+
+    /// Sets the acceptable states for the device; the contract is broken if
+    /// the device leaves these states.
+    pub fn aset<S: Into<DeviceStateSet>>(
+        mut self,
+        states: S,
+    ) -> DeviceTemplateBuilder {
+        self.aset = Some(states.into());
+        self
+    }
+
+    /// Sets the /devices path of the minor node the contract covers.
+    pub fn minor<S: Into<String>>(mut self, minor: S) -> DeviceTemplateBuilder {
+        self.minor = Some(minor.into());
+        self
+    }
+
+    /// Controls whether state changes outside the acceptable set are made
+    /// without negotiating with the contract holder.
+    pub fn noneg(mut self, noneg: bool) -> DeviceTemplateBuilder {
+        self.noneg = Some(noneg);
+        self
+    }
+
+    pub fn critical(mut self, events: DevEventSet) -> DeviceTemplateBuilder {
+        self.critical = Some(events);
+        self
+    }
+
+    pub fn informative(mut self, events: DevEventSet) -> DeviceTemplateBuilder {
+        self.informative = Some(events);
+        self
+    }
+
+    pub fn cookie(mut self, cookie: u64) -> DeviceTemplateBuilder {
+        self.cookie = Some(cookie);
+        self
+    }
+
+    /// Opens a new device contract template and applies the configured terms
+    /// to it.
+    pub fn build(&self) -> Result<Template, ContractError> {
+        let minor = match &self.minor {
+            Some(minor) => Some(validate_minor(minor)?),
+            None => None,
+        };
+
+        let tmpl = Template::open(ctfs::DEVICE_TEMPLATE)?;
+
+        if let Some(aset) = self.aset {
+            tmpl.set_aset(aset)?;
+        }
+        if let Some(minor) = minor {
+            tmpl.set_minor(&minor)?;
+        }
+        match self.noneg {
+            Some(true) => tmpl.set_noneg()?,
+            Some(false) => tmpl.clear_noneg()?,
+            None => (),
+        }
+        if let Some(events) = self.critical {
+            tmpl.set_critical(events)?;
+        }
+        if let Some(events) = self.informative {
+            tmpl.set_informative(events)?;
+        }
+        if let Some(cookie) = self.cookie {
+            tmpl.set_cookie(cookie)?;
+        }
+
+        Ok(tmpl)
+    }
+
+    /// Builds the template and makes it the active device contract template
+    /// for the calling thread.
+    pub fn activate(&self) -> Result<Template, ContractError> {
+        let tmpl = self.build()?;
+        tmpl.activate()?;
+        Ok(tmpl)
+    }
+
+    /// Builds the template and creates a new contract from it, returning the
+    /// ID of the new contract.
+    pub fn create(&self) -> Result<ContractId, ContractError> {
+        self.build()?.create()
+    }
+}
+
+fn validate_minor(minor: &str) -> Result<CString, ContractError> {
+    if !minor.starts_with(DEVICES_PREFIX)
+        || minor.len() <= DEVICES_PREFIX.len()
+        || minor.len() >= MAXPATHLEN
+    {
+        return Err(ContractError::InvalidArgument);
+    }
+
+    cstring(minor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_set_from_raw() {
+        assert!(DeviceStateSet::from_raw(0).unwrap().is_empty());
+        assert_eq!(
+            DeviceStateSet::from_raw(
+                sys::CT_DEV_EV_ONLINE | sys::CT_DEV_EV_DEGRADED
+            )
+            .unwrap(),
+            DeviceStateSet::USABLE
+        );
+        assert_eq!(
+            DeviceStateSet::from_raw(sys::CT_DEV_ALLEVENT).unwrap(),
+            DeviceStateSet::ALL
+        );
+        assert!(matches!(
+            DeviceStateSet::from_raw(0x8),
+            Err(ContractError::InvalidArgument)
+        ));
+        assert!(DeviceStateSet::try_from(sys::CT_DEV_ALLEVENT | 0x10).is_err());
+    }
+
+    #[test]
+    fn state_set_contents() {
+        let set: DeviceStateSet =
+            [DeviceState::Offline, DeviceState::Online].into_iter().collect();
+        assert!(set.contains(DeviceState::Online));
+        assert!(!set.contains(DeviceState::Degraded));
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            [DeviceState::Online, DeviceState::Offline]
+        );
+        assert_eq!(
+            DeviceStateSet::from(DeviceState::Degraded),
+            DeviceStateSet::DEGRADED
+        );
+        assert_eq!(
+            c_uint::from(set),
+            sys::CT_DEV_EV_ONLINE | sys::CT_DEV_EV_OFFLINE
+        );
+        assert_eq!(DeviceStateSet::empty(), DeviceStateSet::default());
+        assert_eq!(DeviceStateSet::empty().iter().count(), 0);
+    }
+
+    #[test]
+    fn state_from_raw() {
+        for state in DeviceStateSet::ALL.iter() {
+            assert_eq!(DeviceState::from_raw(state.as_raw()), Some(state));
+        }
+        assert_eq!(DeviceState::from_raw(0), None);
+        assert_eq!(
+            DeviceState::from_raw(
+                sys::CT_DEV_EV_ONLINE | sys::CT_DEV_EV_OFFLINE
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::fs::ReadDir;
+use std::io;
+
+use super::{supported, ContractError, Detail, StatusSnapshot};
+use crate::ids::{ContractId, ZoneId};
+use crate::{ct_typeid_t, ctstate_t};
+
+/// Returns an iterator over every contract visible to the caller, read from
+/// /system/contract/all.  Contracts are read lazily, one at a time, with
+/// [`Detail::Common`] unless [`AllContracts::detail`] says otherwise.
+pub fn all_contracts() -> Result<AllContracts, ContractError> {
+    supported()?;
+
+    Ok(AllContracts {
+        entries: entries()?,
+        detail: Detail::Common,
+        type_name: None,
+        zoneid: None,
+        state: None,
+    })
+}
+
+/// Returns an iterator over the orphaned contracts visible to the caller,
+/// i.e., those in state CTS_ORPHAN whose holder has gone away without a
+/// regent to inherit them.  Such contracts may be adopted with
+/// [`super::Contract::adopt_id`].
+pub fn orphans() -> Result<AllContracts, ContractError> {
+    Ok(all_contracts()?.in_state(ctstate_t::CTS_ORPHAN))
+}
+
+/// An iterator over contracts, returned by [`all_contracts`].
+///
+/// Contracts that go away between being listed and their status being read
+/// are skipped, as are contracts that do not match the configured filters.
+#[derive(Debug)]
+pub struct AllContracts {
+    entries: Entries,
+    detail: Detail,
+    type_name: Option<&'static str>,
+    zoneid: Option<ZoneId>,
+    state: Option<ctstate_t>,
+}
+
+impl AllContracts {
+    /// Sets the level of detail with which the status of each contract is
+    /// read.
+    pub fn detail(mut self, detail: Detail) -> AllContracts {
+        self.detail = detail;
+        self
+    }
+
+    /// Only yields contracts of type `ty`.  A type unknown to this crate
+    /// matches no contracts.
+    pub fn of_type(mut self, ty: ct_typeid_t) -> AllContracts {
+        self.type_name = Some(ty.type_name().unwrap_or(""));
+        self
+    }
+
+    /// Only yields contracts that belong to zone `zoneid`.  With the "zones"
+    /// feature, a zone may be found by name with `ZoneId::by_name`.
+    pub fn in_zone(mut self, zoneid: ZoneId) -> AllContracts {
+        self.zoneid = Some(zoneid);
+        self
+    }
+
+    /// Only yields contracts in state `state`.
+    pub fn in_state(mut self, state: ctstate_t) -> AllContracts {
+        self.state = Some(state);
+        self
+    }
+
+    fn matches(&self, st: &StatusSnapshot) -> bool {
+        self.type_name.is_none_or(|t| st.type_name == t)
+            && self.zoneid.is_none_or(|z| st.zoneid == z)
+            && self.state.is_none_or(|s| st.state == s)
+    }
+}
+
+impl Iterator for AllContracts {
+    type Item = Result<(ContractId, StatusSnapshot), ContractError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ctid = match self.entries.next()? {
+                Ok(Some(ctid)) => ctid,
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            match StatusSnapshot::read(ctid, self.detail) {
+                Ok(st) if self.matches(&st) => return Some(Ok((ctid, st))),
+                Ok(_) => continue,
+                Err(ContractError::NotFound)
+                | Err(ContractError::NoSuchContract) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/*
+ * The entries of /system/contract/all, or of the simulated contract file
+ * system.
+ */
+#[derive(Debug)]
+enum Entries {
+    Dir(ReadDir),
+    #[cfg(feature = "mock")]
+    Mock(std::vec::IntoIter<crate::ctid_t>),
+}
+
+#[cfg(not(feature = "mock"))]
+fn entries() -> Result<Entries, ContractError> {
+    Ok(Entries::Dir(std::fs::read_dir(crate::ctfs::CTFS_ALL)?))
+}
+
+#[cfg(feature = "mock")]
+fn entries() -> Result<Entries, ContractError> {
+    Ok(Entries::Mock(crate::mock::contract_ids().into_iter()))
+}
+
+impl Iterator for Entries {
+    /*
+     * The ID of the contract an entry is for, or None for an entry that is
+     * not named for a contract ID; every entry should be, but anything that
+     * is not is skipped rather than failing.
+     */
+    type Item = io::Result<Option<ContractId>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Entries::Dir(dir) => Some(dir.next()?.map(|ent| {
+                ent.file_name().to_str().and_then(|n| n.parse().ok())
+            })),
+            #[cfg(feature = "mock")]
+            Entries::Mock(ids) => {
+                ids.next().map(|ctid| Ok(Some(ContractId(ctid))))
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::fmt;
+use std::io;
+
+/// An error from a contract operation.
+///
+/// The variants correspond to the error numbers documented for the
+/// ct_tmpl_*(3CONTRACT), ct_ctl_*(3CONTRACT), ct_event_read(3CONTRACT) and
+/// ct_status_read(3CONTRACT) families of functions.  Errors that have no
+/// particular meaning for contracts are preserved as [`ContractError::Io`].
+#[derive(Debug)]
+pub enum ContractError {
+    /// EINVAL: an argument or a combination of template terms is invalid, or
+    /// the contract is not in a state that permits the operation.
+    InvalidArgument,
+    /// EBADF: the file descriptor is not open, or is not the right kind of
+    /// contract endpoint for the operation.
+    BadDescriptor,
+    /// EPERM: the caller lacks the privilege needed, e.g., to set a critical
+    /// event outside the default set without {PRIV_CONTRACT_EVENT}.
+    PermissionDenied,
+    /// EACCES: the caller may not access the contract endpoint.
+    AccessDenied,
+    /// EAGAIN: no event is available on a non-blocking event endpoint.
+    WouldBlock,
+    /// ESRCH: no such contract, or the event ID does not refer to an event
+    /// awaiting a response.
+    NoSuchContract,
+    /// EBUSY: the contract is already owned.
+    Busy,
+    /// ENOENT: the contract or endpoint does not exist.
+    NotFound,
+    /// ENOTSUP: the operation is not supported for this contract type.
+    NotSupported,
+    /// EOVERFLOW: a value did not fit in the space available for it.
+    Overflow,
+    /// EINTR: the operation was interrupted by a signal.
+    Interrupted,
+    /// The event does not allow the requested response, e.g., because it is
+    /// informative or has already been acknowledged.  This is detected
+    /// before the response is sent, and carries the ESRCH the system would
+    /// otherwise have reported.
+    ResponseNotAllowed,
+    /// EACCES from ct_ctl_adopt(3CONTRACT): the contract has not been
+    /// inherited by, or orphaned for, the caller, and so may not be adopted.
+    NotInheritable,
+    /// The contract still has member processes, and so may not be the
+    /// subject of a transfer; see [`super::Template::set_transfer`].  This
+    /// carries the ENOTEMPTY the system would report.
+    NotEmpty,
+    /// The contract is held by some other process, or by none, and so may
+    /// not be the subject of a transfer.  This carries the EACCES the
+    /// system would report.
+    NotHeld,
+    /// The contract is not a process contract, and so may not be the
+    /// subject of a transfer to a process contract.  This carries the ESRCH
+    /// the system would report.
+    NotProcessContract,
+    /// Any other error.
+    Io(io::Error),
+}
+
+impl ContractError {
+    /// Constructs an error from an error number, as returned by most
+    /// libcontract(3LIB) functions.
+    pub fn from_errno(errno: i32) -> ContractError {
+        match errno {
+            libc::EINVAL => ContractError::InvalidArgument,
+            libc::EBADF => ContractError::BadDescriptor,
+            libc::EPERM => ContractError::PermissionDenied,
+            libc::EACCES => ContractError::AccessDenied,
+            libc::EAGAIN => ContractError::WouldBlock,
+            libc::ESRCH => ContractError::NoSuchContract,
+            libc::EBUSY => ContractError::Busy,
+            libc::ENOENT => ContractError::NotFound,
+            libc::ENOTSUP => ContractError::NotSupported,
+            libc::EOVERFLOW => ContractError::Overflow,
+            libc::EINTR => ContractError::Interrupted,
+            e => ContractError::Io(io::Error::from_raw_os_error(e)),
+        }
+    }
+
+    /// Returns the error number for this error, if it has one.
+    pub fn errno(&self) -> Option<i32> {
+        Some(match self {
+            ContractError::InvalidArgument => libc::EINVAL,
+            ContractError::BadDescriptor => libc::EBADF,
+            ContractError::PermissionDenied => libc::EPERM,
+            ContractError::AccessDenied => libc::EACCES,
+            ContractError::WouldBlock => libc::EAGAIN,
+            ContractError::NoSuchContract => libc::ESRCH,
+            ContractError::Busy => libc::EBUSY,
+            ContractError::NotFound => libc::ENOENT,
+            ContractError::NotSupported => libc::ENOTSUP,
+            ContractError::Overflow => libc::EOVERFLOW,
+            ContractError::Interrupted => libc::EINTR,
+            ContractError::ResponseNotAllowed => libc::ESRCH,
+            ContractError::NotInheritable => libc::EACCES,
+            ContractError::NotEmpty => libc::ENOTEMPTY,
+            ContractError::NotHeld => libc::EACCES,
+            ContractError::NotProcessContract => libc::ESRCH,
+            ContractError::Io(e) => return e.raw_os_error(),
+        })
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            ContractError::Io(e) => e.kind(),
+            other => {
+                /*
+                 * All other variants carry an error number.
+                 */
+                io::Error::from_raw_os_error(other.errno().unwrap()).kind()
+            }
+        }
+    }
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ContractError::InvalidArgument => "invalid argument",
+            ContractError::BadDescriptor => "bad contract file descriptor",
+            ContractError::PermissionDenied => "insufficient privileges",
+            ContractError::AccessDenied => "access to contract denied",
+            ContractError::WouldBlock => "no event available",
+            ContractError::NoSuchContract => "no such contract or event",
+            ContractError::Busy => "contract is already owned",
+            ContractError::NotFound => "contract or endpoint not found",
+            ContractError::NotSupported => "operation not supported",
+            ContractError::Overflow => "value too large",
+            ContractError::Interrupted => "interrupted",
+            ContractError::ResponseNotAllowed => {
+                "event does not allow that response"
+            }
+            ContractError::NotInheritable => "contract may not be adopted",
+            ContractError::NotEmpty => "contract has member processes",
+            ContractError::NotHeld => "contract is not held by the caller",
+            ContractError::NotProcessContract => {
+                "contract is not a process contract"
+            }
+            ContractError::Io(e) => return write!(f, "{}", e),
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for ContractError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ContractError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ContractError {
+    fn from(e: io::Error) -> ContractError {
+        match e.raw_os_error() {
+            Some(errno) => ContractError::from_errno(errno),
+            None => ContractError::Io(e),
+        }
+    }
+}
+
+impl From<ContractError> for io::Error {
+    fn from(e: ContractError) -> io::Error {
+        match e {
+            ContractError::Io(e) => e,
+            other => io::Error::from_raw_os_error(other.errno().unwrap()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::discriminant;
+
+    use super::*;
+
+    /*
+     * Every variant that carries an error number, and the variant that error
+     * number decodes to: the variants that only refine the meaning of an
+     * error number for one operation decode to the general one.
+     */
+    fn variants() -> Vec<(ContractError, ContractError)> {
+        use ContractError::*;
+
+        vec![
+            (InvalidArgument, InvalidArgument),
+            (BadDescriptor, BadDescriptor),
+            (PermissionDenied, PermissionDenied),
+            (AccessDenied, AccessDenied),
+            (WouldBlock, WouldBlock),
+            (NoSuchContract, NoSuchContract),
+            (Busy, Busy),
+            (NotFound, NotFound),
+            (NotSupported, NotSupported),
+            (Overflow, Overflow),
+            (Interrupted, Interrupted),
+            (ResponseNotAllowed, NoSuchContract),
+            (NotInheritable, AccessDenied),
+            (NotEmpty, Io(io::Error::from_raw_os_error(libc::ENOTEMPTY))),
+            (NotHeld, AccessDenied),
+            (NotProcessContract, NoSuchContract),
+        ]
+    }
+
+    #[test]
+    fn errno_round_trip() {
+        for (e, decoded) in variants() {
+            let errno = e.errno().unwrap();
+            let again = ContractError::from_errno(errno);
+            assert_eq!(discriminant(&again), discriminant(&decoded), "{e:?}");
+            assert_eq!(again.errno(), Some(errno));
+
+            let ioe = io::Error::from_raw_os_error(errno);
+            assert_eq!(e.kind(), ioe.kind(), "{e:?}");
+            assert_eq!(io::Error::from(e).raw_os_error(), Some(errno));
+            let from_io = ContractError::from(ioe);
+            assert_eq!(discriminant(&from_io), discriminant(&decoded));
+        }
+    }
+
+    #[test]
+    fn other_errors() {
+        let e = ContractError::from_errno(libc::EIO);
+        assert!(matches!(&e, ContractError::Io(_)));
+        assert_eq!(e.errno(), Some(libc::EIO));
+        assert_eq!(io::Error::from(e).raw_os_error(), Some(libc::EIO));
+
+        let e = ContractError::from(io::Error::other("no errno"));
+        assert!(matches!(&e, ContractError::Io(_)));
+        assert_eq!(e.errno(), None);
+        assert_eq!(e.kind(), io::ErrorKind::Other);
+        assert_eq!(e.to_string(), "no errno");
+        assert!(std::error::Error::source(&e).is_some());
+        assert!(std::error::Error::source(&ContractError::Busy).is_none());
+    }
+}
@@ -0,0 +1,388 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::raw::{c_int, c_uint};
+use std::path::Path;
+use std::ptr;
+use std::sync::Mutex;
+
+use super::{check, impl_as_fd, open_read, stats};
+use super::{Contract, ContractError, CteFlags, Detail, Status};
+use crate as sys;
+use crate::ctfs;
+use crate::ids::{ContractId, EventId, ZoneId};
+use crate::{ct_evthdl_t, ct_typeid_t};
+
+/// The kind of an [`EventEndpoint`]; see contract(5).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EndpointKind {
+    /// The endpoint of a single contract.
+    Contract,
+    /// The bundle of a contract type, which carries events for every
+    /// contract of that type.
+    Bundle,
+    /// The process bundle of a contract type, which carries events for the
+    /// contracts of that type held by the reading process.
+    ProcessBundle,
+}
+
+/// An event endpoint, from which contract events are read; see
+/// ct_event_read(3CONTRACT).
+///
+/// Events read from a [`EndpointKind::Bundle`] endpoint are delivered to
+/// observers, who do not generally hold the contracts concerned, and so
+/// [`Event::ack`] and friends reject them; the holder of a contract should
+/// read from the contract's endpoint or the process bundle instead.
+///
+/// Iterating over an endpoint reads events one at a time, blocking until
+/// each is available.  If the endpoint was opened with O_NONBLOCK, iteration
+/// instead ends once no more events are queued.
+#[derive(Debug)]
+pub struct EventEndpoint {
+    fd: OwnedFd,
+    kind: EndpointKind,
+    zoneid: Option<ZoneId>,
+    zones: Mutex<HashMap<ContractId, ZoneId>>,
+}
+
+/*
+ * The number of contracts whose zones an endpoint filtering by zone remembers
+ * before it forgets those of contracts that have gone away.
+ */
+const ZONE_CACHE_LIMIT: usize = 1024;
+
+impl EventEndpoint {
+    /// Opens the event endpoint at `path`.  The kind of the endpoint is
+    /// determined from the final component of the path.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<EventEndpoint, ContractError> {
+        let path = path.as_ref();
+        let kind = match path.file_name().and_then(|n| n.to_str()) {
+            Some("bundle") => EndpointKind::Bundle,
+            Some("pbundle") => EndpointKind::ProcessBundle,
+            _ => EndpointKind::Contract,
+        };
+
+        Ok(EventEndpoint {
+            fd: open_read(path)?,
+            kind,
+            zoneid: None,
+            zones: Mutex::default(),
+        })
+    }
+
+    /// Opens the event endpoint of the contract with ID `ctid`.
+    pub fn for_contract(
+        ctid: ContractId,
+    ) -> Result<EventEndpoint, ContractError> {
+        EventEndpoint::open(ctfs::events_path(ctid.as_raw()))
+    }
+
+    /// Opens the bundle endpoint for contracts of type `ty`.
+    pub fn bundle(ty: ct_typeid_t) -> Result<EventEndpoint, ContractError> {
+        let name = ty.type_name().ok_or(ContractError::NotSupported)?;
+        EventEndpoint::open(ctfs::bundle_path(name))
+    }
+
+    /// Opens the process bundle endpoint for contracts of type `ty`.
+    pub fn pbundle(ty: ct_typeid_t) -> Result<EventEndpoint, ContractError> {
+        let name = ty.type_name().ok_or(ContractError::NotSupported)?;
+        EventEndpoint::open(ctfs::pbundle_path(name))
+    }
+
+    pub fn kind(&self) -> EndpointKind {
+        self.kind
+    }
+
+    /// Only yields events from contracts that belong to zone `zoneid`, for
+    /// observers in the global zone, whose bundles carry the events of
+    /// contracts in every zone.  Events from other zones are read and
+    /// discarded.
+    ///
+    /// The zone of each contract is found by reading its status the first
+    /// time one of its events is read, and remembered from then on.  An
+    /// event from a contract that has already gone away when the first of
+    /// its events is read, such as the EMPTY event of an orphaned contract,
+    /// cannot be placed in a zone and is yielded regardless.
+    pub fn in_zone(mut self, zoneid: ZoneId) -> EventEndpoint {
+        self.zoneid = Some(zoneid);
+        self
+    }
+
+    /// Sets or clears O_NONBLOCK on the endpoint.  Reads from a non-blocking
+    /// endpoint fail with [`ContractError::WouldBlock`] when no event is
+    /// available.
+    pub fn set_nonblocking(
+        &self,
+        nonblocking: bool,
+    ) -> Result<(), ContractError> {
+        let flags = unsafe { libc::fcntl(self.fd.as_raw_fd(), libc::F_GETFL) };
+        if flags < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        let flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+        if unsafe { libc::fcntl(self.fd.as_raw_fd(), libc::F_SETFL, flags) } < 0
+        {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(())
+    }
+
+    /// Reads the next event from the endpoint.
+    pub fn read(&self) -> Result<Event, ContractError> {
+        self.read_with(|fd, hdl| unsafe { sys::ct_event_read(fd, hdl) })
+    }
+
+    /// Reads the next critical event from the endpoint.
+    pub fn read_critical(&self) -> Result<Event, ContractError> {
+        self.read_with(|fd, hdl| unsafe {
+            sys::ct_event_read_critical(fd, hdl)
+        })
+    }
+
+    fn read_with(
+        &self,
+        read: impl Fn(c_int, &mut *mut ct_evthdl_t) -> c_int,
+    ) -> Result<Event, ContractError> {
+        loop {
+            let mut hdl = ptr::null_mut();
+            check(read(self.fd.as_raw_fd(), &mut hdl))?;
+            let ev = Event { hdl, source: self.kind };
+            if self.in_zone_of(&ev)? {
+                stats::event_received(&ev);
+                return Ok(ev);
+            }
+        }
+    }
+
+    fn in_zone_of(&self, ev: &Event) -> Result<bool, ContractError> {
+        let Some(zoneid) = self.zoneid else {
+            return Ok(true);
+        };
+
+        let mut zones = self.zones.lock().unwrap();
+        if let Some(&zone) = zones.get(&ev.ctid()) {
+            return Ok(zone == zoneid);
+        }
+
+        let zone = match Status::read_ctid(ev.ctid(), Detail::Common) {
+            Ok(st) => st.zoneid(),
+            Err(ContractError::NotFound) => return Ok(true),
+            Err(e) => return Err(e),
+        };
+        if zones.len() >= ZONE_CACHE_LIMIT {
+            zones.retain(|&ctid, _| {
+                !matches!(
+                    Status::read_ctid(ctid, Detail::Common),
+                    Err(ContractError::NotFound)
+                )
+            });
+        }
+        zones.insert(ev.ctid(), zone);
+        Ok(zone == zoneid)
+    }
+
+    /// Resets the endpoint so that the next read returns the oldest event
+    /// still in the queue.
+    pub fn reset(&self) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_event_reset(self.fd.as_raw_fd()) })
+    }
+
+    /// Requests reliable delivery of events; this requires the
+    /// {PRIV_CONTRACT_OBSERVER} privilege.
+    pub fn reliable(&self) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_event_reliable(self.fd.as_raw_fd()) })
+    }
+}
+
+impl Iterator for EventEndpoint {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        next_event(self)
+    }
+}
+
+impl Iterator for &EventEndpoint {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        next_event(self)
+    }
+}
+
+fn next_event(ep: &EventEndpoint) -> Option<io::Result<Event>> {
+    match ep.read() {
+        Ok(ev) => Some(Ok(ev)),
+        Err(ContractError::WouldBlock) => None,
+        Err(e) => Some(Err(e.into())),
+    }
+}
+
+impl From<OwnedFd> for EventEndpoint {
+    /// Wraps an already open event endpoint, which is assumed to be the
+    /// endpoint of a single contract.
+    fn from(fd: OwnedFd) -> EventEndpoint {
+        EventEndpoint {
+            fd,
+            kind: EndpointKind::Contract,
+            zoneid: None,
+            zones: Mutex::default(),
+        }
+    }
+}
+
+impl_as_fd!(EventEndpoint, fd);
+
+/*
+ * Event endpoints are registered for readability; endpoints used this way
+ * should be put in non-blocking mode with EventEndpoint::set_nonblocking().
+ */
+#[cfg(feature = "mio")]
+impl mio::event::Source for EventEndpoint {
+    fn register(
+        &mut self,
+        registry: &mio::Registry,
+        token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        mio::unix::SourceFd(&self.fd.as_raw_fd())
+            .register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &mio::Registry,
+        token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        mio::unix::SourceFd(&self.fd.as_raw_fd())
+            .reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        mio::unix::SourceFd(&self.fd.as_raw_fd()).deregister(registry)
+    }
+}
+
+/// A contract event read from an [`EventEndpoint`].
+#[derive(Debug)]
+pub struct Event {
+    hdl: *mut ct_evthdl_t,
+    source: EndpointKind,
+}
+
+impl Event {
+    /// Returns the ID of the contract that generated this event.
+    pub fn ctid(&self) -> ContractId {
+        ContractId(unsafe { sys::ct_event_get_ctid(self.hdl) })
+    }
+
+    pub fn evid(&self) -> EventId {
+        EventId(unsafe { sys::ct_event_get_evid(self.hdl) })
+    }
+
+    pub fn flags(&self) -> CteFlags {
+        CteFlags::from_bits_retain(unsafe { sys::ct_event_get_flags(self.hdl) })
+    }
+
+    /// Returns the event type, e.g., CT_PR_EV_EXIT or CT_EV_NEGEND.
+    pub fn event_type(&self) -> c_uint {
+        unsafe { sys::ct_event_get_type(self.hdl) }
+    }
+
+    /// For a CT_EV_NEGEND event, returns the ID of the negotiation event
+    /// that completed.
+    pub fn nevid(&self) -> Result<EventId, ContractError> {
+        let mut evid = 0;
+        check(unsafe { sys::ct_event_get_nevid(self.hdl, &mut evid) })?;
+        Ok(EventId(evid))
+    }
+
+    /// For a CT_EV_NEGEND event, returns the ID of the contract that
+    /// replaced the one that generated the event.
+    pub fn newct(&self) -> Result<ContractId, ContractError> {
+        let mut ctid = 0;
+        check(unsafe { sys::ct_event_get_newct(self.hdl, &mut ctid) })?;
+        Ok(ContractId(ctid))
+    }
+
+    /// Returns the kind of endpoint the event was read from.
+    pub fn source(&self) -> EndpointKind {
+        self.source
+    }
+
+    /// Acknowledges this critical event on `ct`, the contract that
+    /// generated it.  Informative events, events that have already been
+    /// acknowledged, and events read from a bundle are rejected with
+    /// [`ContractError::ResponseNotAllowed`].
+    pub fn ack(&self, ct: &Contract) -> Result<(), ContractError> {
+        self.check_response(ct, CteFlags::empty())?;
+        ct.ack(self.evid())
+    }
+
+    /// Refuses the change proposed by this negotiation event.  Events without
+    /// the CTE_NEG flag are rejected with
+    /// [`ContractError::ResponseNotAllowed`].
+    pub fn nack(&self, ct: &Contract) -> Result<(), ContractError> {
+        self.check_response(ct, CteFlags::NEG)?;
+        ct.nack(self.evid())
+    }
+
+    /// Requests more time to respond to this negotiation event.
+    pub fn qack(&self, ct: &Contract) -> Result<(), ContractError> {
+        self.check_response(ct, CteFlags::NEG)?;
+        ct.qack(self.evid())
+    }
+
+    fn check_response(
+        &self,
+        ct: &Contract,
+        required: CteFlags,
+    ) -> Result<(), ContractError> {
+        if ct.id() != self.ctid() {
+            return Err(ContractError::InvalidArgument);
+        }
+
+        if self.source == EndpointKind::Bundle {
+            return Err(ContractError::ResponseNotAllowed);
+        }
+
+        let flags = self.flags();
+        if flags.intersects(CteFlags::INFO | CteFlags::ACK)
+            || !flags.contains(required)
+        {
+            return Err(ContractError::ResponseNotAllowed);
+        }
+
+        Ok(())
+    }
+
+    pub fn as_ptr(&self) -> *mut ct_evthdl_t {
+        self.hdl
+    }
+}
+
+/*
+ * The event handle is allocated by ct_event_read(3CONTRACT) for the
+ * exclusive use of its reader, and may be used and freed from any thread.
+ */
+unsafe impl Send for Event {}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe { sys::ct_event_free(self.hdl) };
+    }
+}
@@ -0,0 +1,370 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::ffi::CStr;
+use std::fmt::{self, Write};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use super::{CteFlags, DeviceState, Event, PrEventSet};
+use crate as sys;
+use crate::ctfs;
+use crate::ids::{ContractId, EventId};
+
+/*
+ * A field of an exported event.
+ */
+enum Value {
+    Int(i64),
+    Str(String),
+    Bool(bool),
+}
+
+/*
+ * The fields of an exported event: the IDs and flags, which every event has
+ * and which the one-line format shows by position, and then the fields that
+ * depend on the kind of event.
+ */
+struct Fields {
+    ctid: ContractId,
+    evid: EventId,
+    flags: CteFlags,
+    details: Vec<(&'static str, Value)>,
+}
+
+impl Fields {
+    fn common(&self) -> [(&'static str, Value); 5] {
+        let flags = self.flags;
+        [
+            ("ctid", Value::Int(self.ctid.as_raw().into())),
+            ("evid", Value::Int(self.evid.as_raw() as i64)),
+            ("critical", Value::Bool(!flags.contains(CteFlags::INFO))),
+            ("acked", Value::Bool(flags.contains(CteFlags::ACK))),
+            ("negotiation", Value::Bool(flags.contains(CteFlags::NEG))),
+        ]
+    }
+
+    fn into_json(self) -> String {
+        let mut out = String::from("{");
+        let all = self.common().into_iter().chain(self.details);
+
+        for (i, (name, value)) in all.enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            json_str(&mut out, name);
+            out.push(':');
+            match value {
+                Value::Int(n) => write!(out, "{}", n).unwrap(),
+                Value::Str(s) => json_str(&mut out, &s),
+                Value::Bool(b) => write!(out, "{}", b).unwrap(),
+            }
+        }
+
+        out.push('}');
+        out
+    }
+}
+
+impl Event {
+    /// Formats the event as a single-line JSON object, suitable for
+    /// structured logging.  The field names are stable:
+    ///
+    /// - `ctid`, `evid`: the contract and event IDs.
+    /// - `critical`, `acked`, `negotiation`: the event flags.
+    /// - `contract_type`: "process" or "device".  Events for which the type
+    ///   cannot be determined, such as CT_EV_NEGEND, omit this field.
+    /// - `type`: the event type, e.g., "exit" or "offline", or the number if
+    ///   the type is not known.
+    /// - `pid`, `ppid`, `exit_status`, `signal`, `sender`, `sender_ctid`,
+    ///   `pcorefile`, `gcorefile`, `zcorefile`: for process events that
+    ///   carry them.
+    /// - `nevid`, `newct`: for CT_EV_NEGEND events.
+    pub fn to_json(&self) -> String {
+        self.fields().into_json()
+    }
+
+    fn fields(&self) -> Fields {
+        Fields {
+            ctid: self.ctid(),
+            evid: self.evid(),
+            flags: self.flags(),
+            details: self.details(),
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, Value)> {
+        let mut out = Vec::new();
+
+        let ty = self.event_type();
+        let pid = self.pr_int(sys::ct_pr_event_get_pid);
+
+        if ty == sys::CT_EV_NEGEND {
+            out.push(("type", Value::Str("negend".into())));
+            if let Ok(nevid) = self.nevid() {
+                out.push(("nevid", Value::Int(nevid.as_raw() as i64)));
+            }
+            if let Ok(newct) = self.newct() {
+                out.push(("newct", Value::Int(newct.as_raw().into())));
+            }
+        } else if let Some(pid) = pid {
+            /*
+             * Only process events carry a process ID.
+             */
+            out.push(("contract_type", Value::Str(ctfs::PROCESS_TYPE.into())));
+            out.push(("type", type_value(pr_event_name(ty), ty)));
+            out.push(("pid", Value::Int(pid)));
+
+            let ints: [(&str, PrIntFn); 5] = [
+                ("ppid", sys::ct_pr_event_get_ppid),
+                ("exit_status", sys::ct_pr_event_get_exitstatus),
+                ("signal", sys::ct_pr_event_get_signal),
+                ("sender", sys::ct_pr_event_get_sender),
+                ("sender_ctid", sys::ct_pr_event_get_senderct),
+            ];
+            for (name, get) in ints {
+                if let Some(n) = self.pr_int(get) {
+                    out.push((name, Value::Int(n)));
+                }
+            }
+
+            let strs: [(&str, PrStrFn); 3] = [
+                ("pcorefile", sys::ct_pr_event_get_pcorefile),
+                ("gcorefile", sys::ct_pr_event_get_gcorefile),
+                ("zcorefile", sys::ct_pr_event_get_zcorefile),
+            ];
+            for (name, get) in strs {
+                if let Some(s) = self.pr_str(get) {
+                    out.push((name, Value::Str(s)));
+                }
+            }
+        } else {
+            out.push(("contract_type", Value::Str(ctfs::DEVICE_TYPE.into())));
+            let name = DeviceState::from_raw(ty).map(dev_event_name);
+            out.push(("type", type_value(name, ty)));
+        }
+
+        out
+    }
+
+    /*
+     * Returns the name of the event type, as used in the "type" field, if it
+     * is known.
+     */
+    pub(super) fn type_name(&self) -> Option<&'static str> {
+        let ty = self.event_type();
+        if ty == sys::CT_EV_NEGEND {
+            Some("negend")
+        } else if self.pr_int(sys::ct_pr_event_get_pid).is_some() {
+            pr_event_name(ty)
+        } else {
+            DeviceState::from_raw(ty).map(dev_event_name)
+        }
+    }
+
+    fn pr_int(&self, get: PrIntFn) -> Option<i64> {
+        let mut n = 0;
+        let rv = unsafe { get(self.as_ptr(), &mut n) };
+        (rv == 0).then_some(n.into())
+    }
+
+    fn pr_str(&self, get: PrStrFn) -> Option<String> {
+        let mut p = ptr::null_mut();
+        let rv = unsafe { get(self.as_ptr(), &mut p) };
+        if rv != 0 || p.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned())
+    }
+}
+
+/// Formats the event on one line, in the manner of ctwatch(1): the contract
+/// ID, event ID, "crit" or "info", "ack" or "-", "neg" or "-", and then the
+/// remaining fields of [`Event::to_json`] as `name=value` pairs.
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fields().fmt(f)
+    }
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = self.flags;
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.ctid,
+            self.evid,
+            if flags.contains(CteFlags::INFO) { "info" } else { "crit" },
+            if flags.contains(CteFlags::ACK) { "ack" } else { "-" },
+            if flags.contains(CteFlags::NEG) { "neg" } else { "-" },
+        )?;
+
+        for (name, value) in &self.details {
+            match value {
+                Value::Int(n) => write!(f, " {}={}", name, n)?,
+                Value::Str(s) => write!(f, " {}={:?}", name, s)?,
+                Value::Bool(b) => write!(f, " {}={}", name, b)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/*
+ * The process event accessors that return integers all use pid_t, ctid_t or
+ * int, which are the same type on illumos.
+ */
+type PrIntFn = unsafe extern "C" fn(*mut sys::ct_evthdl_t, *mut c_int) -> c_int;
+type PrStrFn =
+    unsafe extern "C" fn(*mut sys::ct_evthdl_t, *mut *mut c_char) -> c_int;
+
+fn type_value(name: Option<&'static str>, ty: u32) -> Value {
+    match name {
+        Some(name) => Value::Str(name.into()),
+        None => Value::Int(ty.into()),
+    }
+}
+
+fn pr_event_name(ty: u32) -> Option<&'static str> {
+    let ev = PrEventSet::from_bits_retain(ty);
+    Some(match ev {
+        PrEventSet::EMPTY => "empty",
+        PrEventSet::FORK => "fork",
+        PrEventSet::EXIT => "exit",
+        PrEventSet::CORE => "core",
+        PrEventSet::SIGNAL => "signal",
+        PrEventSet::HWERR => "hwerr",
+        _ => return None,
+    })
+}
+
+fn dev_event_name(state: DeviceState) -> &'static str {
+    match state {
+        DeviceState::Online => "online",
+        DeviceState::Degraded => "degraded",
+        DeviceState::Offline => "offline",
+    }
+}
+
+fn json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(out, "\\u{:04x}", c as u32).unwrap()
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safe::DeviceStateSet;
+
+    fn quoted(s: &str) -> String {
+        let mut out = String::new();
+        json_str(&mut out, s);
+        out
+    }
+
+    #[test]
+    fn json_str_escapes() {
+        assert_eq!(quoted("core.123"), r#""core.123""#);
+        assert_eq!(quoted(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(quoted("\n\r\t"), r#""\n\r\t""#);
+        assert_eq!(quoted("\u{1}\u{1f}"), r#""\u0001\u001f""#);
+        assert_eq!(quoted("\u{7f}é"), "\"\u{7f}é\"");
+    }
+
+    #[test]
+    fn pr_event_names() {
+        let names: Vec<_> = [
+            sys::CT_PR_EV_EMPTY,
+            sys::CT_PR_EV_FORK,
+            sys::CT_PR_EV_EXIT,
+            sys::CT_PR_EV_CORE,
+            sys::CT_PR_EV_SIGNAL,
+            sys::CT_PR_EV_HWERR,
+        ]
+        .into_iter()
+        .map(|ty| pr_event_name(ty).unwrap())
+        .collect();
+        assert_eq!(names, ["empty", "fork", "exit", "core", "signal", "hwerr"]);
+
+        assert_eq!(pr_event_name(0), None);
+        assert_eq!(
+            pr_event_name(sys::CT_PR_EV_CORE | sys::CT_PR_EV_EXIT),
+            None
+        );
+        assert_eq!(pr_event_name(0x1000), None);
+    }
+
+    #[test]
+    fn dev_event_names() {
+        let names: Vec<_> =
+            DeviceStateSet::ALL.iter().map(dev_event_name).collect();
+        assert_eq!(names, ["online", "degraded", "offline"]);
+    }
+
+    #[test]
+    fn unknown_types_are_numbers() {
+        assert!(
+            matches!(type_value(Some("exit"), 4), Value::Str(s) if s == "exit")
+        );
+        assert!(matches!(type_value(None, 0x40), Value::Int(0x40)));
+    }
+
+    fn negotiation() -> Fields {
+        Fields {
+            ctid: ContractId(12),
+            evid: EventId(34),
+            flags: CteFlags::NEG,
+            details: vec![
+                ("contract_type", Value::Str("device".into())),
+                ("type", Value::Str("offline".into())),
+            ],
+        }
+    }
+
+    #[test]
+    fn one_line_format() {
+        assert_eq!(
+            negotiation().to_string(),
+            r#"12 34 crit - neg contract_type="device" type="offline""#
+        );
+
+        let acked = Fields {
+            flags: CteFlags::ACK,
+            details: vec![("pid", Value::Int(100))],
+            ..negotiation()
+        };
+        assert_eq!(acked.to_string(), "12 34 crit ack - pid=100");
+
+        let info =
+            Fields { flags: CteFlags::INFO, details: vec![], ..negotiation() };
+        assert_eq!(info.to_string(), "12 34 info - -");
+    }
+
+    #[test]
+    fn json_format() {
+        assert_eq!(
+            negotiation().into_json(),
+            concat!(
+                r#"{"ctid":12,"evid":34,"critical":true,"acked":false,"#,
+                r#""negotiation":true,"contract_type":"device","#,
+                r#""type":"offline"}"#
+            )
+        );
+    }
+}
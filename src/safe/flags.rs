@@ -0,0 +1,305 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Sub};
+use std::os::raw::c_uint;
+
+use crate as sys;
+
+/*
+ * Defines a set of flags over a c_uint, in the style of the "bitflags"
+ * crate.  Sets may hold bits that have no name here, so that values
+ * returned by the system round-trip even if they include flags that are
+ * newer than this crate.
+ */
+macro_rules! flag_set {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            $($(#[$fattr:meta])* const $flag:ident = $value:expr;)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(transparent)
+        )]
+        #[repr(transparent)]
+        pub struct $name(c_uint);
+
+        impl $name {
+            $($(#[$fattr])* pub const $flag: $name = $name($value);)*
+
+            const NAMED: &'static [(&'static str, $name)] =
+                &[$((stringify!($flag), $name::$flag),)*];
+
+            pub const fn empty() -> $name {
+                $name(0)
+            }
+
+            /// Returns the set of all flags known to this crate.
+            pub const fn all() -> $name {
+                $name(0 $(| $value)*)
+            }
+
+            pub const fn bits(self) -> c_uint {
+                self.0
+            }
+
+            /// Converts raw bits, returning `None` if any bits are set that
+            /// do not correspond to a known flag.
+            pub const fn from_bits(bits: c_uint) -> Option<$name> {
+                if bits & !$name::all().0 == 0 {
+                    Some($name(bits))
+                } else {
+                    None
+                }
+            }
+
+            /// Converts raw bits, discarding any unknown bits.
+            pub const fn from_bits_truncate(bits: c_uint) -> $name {
+                $name(bits & $name::all().0)
+            }
+
+            /// Converts raw bits, keeping any unknown bits.
+            pub const fn from_bits_retain(bits: c_uint) -> $name {
+                $name(bits)
+            }
+
+            pub const fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            pub const fn contains(self, other: $name) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            pub const fn intersects(self, other: $name) -> bool {
+                self.0 & other.0 != 0
+            }
+
+            pub const fn union(self, other: $name) -> $name {
+                $name(self.0 | other.0)
+            }
+
+            pub const fn intersection(self, other: $name) -> $name {
+                $name(self.0 & other.0)
+            }
+
+            pub const fn difference(self, other: $name) -> $name {
+                $name(self.0 & !other.0)
+            }
+
+            pub fn insert(&mut self, other: $name) {
+                self.0 |= other.0;
+            }
+
+            pub fn remove(&mut self, other: $name) {
+                self.0 &= !other.0;
+            }
+
+            /// Returns the names of the known flags in this set, in the
+            /// order in which they are defined.
+            pub fn names(self) -> impl Iterator<Item = &'static str> {
+                $name::NAMED
+                    .iter()
+                    .filter(move |(_, flag)| self.contains(*flag))
+                    .map(|(n, _)| *n)
+            }
+        }
+
+        impl From<c_uint> for $name {
+            fn from(bits: c_uint) -> $name {
+                $name::from_bits_retain(bits)
+            }
+        }
+
+        impl From<$name> for c_uint {
+            fn from(set: $name) -> c_uint {
+                set.0
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = $name;
+
+            fn bitor(self, other: $name) -> $name {
+                self.union(other)
+            }
+        }
+
+        impl BitOrAssign for $name {
+            fn bitor_assign(&mut self, other: $name) {
+                self.insert(other);
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = $name;
+
+            fn bitand(self, other: $name) -> $name {
+                self.intersection(other)
+            }
+        }
+
+        impl BitAndAssign for $name {
+            fn bitand_assign(&mut self, other: $name) {
+                self.0 &= other.0;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                self.difference(other)
+            }
+        }
+
+        impl Not for $name {
+            type Output = $name;
+
+            /*
+             * The complement is taken with respect to the known flags.
+             */
+            fn not(self) -> $name {
+                $name::all().difference(self)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}(", stringify!($name))?;
+                let mut rest = self.0;
+                let mut first = true;
+                for (n, flag) in $name::NAMED {
+                    if self.contains(*flag) {
+                        write!(f, "{}{}", if first { "" } else { " | " }, n)?;
+                        rest &= !flag.0;
+                        first = false;
+                    }
+                }
+                if rest != 0 {
+                    write!(f, "{}{:#x}", if first { "" } else { " | " }, rest)?;
+                } else if first {
+                    write!(f, "empty")?;
+                }
+                write!(f, ")")
+            }
+        }
+    };
+}
+
+flag_set! {
+    /// A set of process contract events; see process(5).
+    pub struct PrEventSet {
+        /// The last member of the contract exited.  Not to be confused
+        /// with [`PrEventSet::empty()`], the set with no events in it.
+        const EMPTY = sys::CT_PR_EV_EMPTY;
+        const FORK = sys::CT_PR_EV_FORK;
+        const EXIT = sys::CT_PR_EV_EXIT;
+        const CORE = sys::CT_PR_EV_CORE;
+        const SIGNAL = sys::CT_PR_EV_SIGNAL;
+        const HWERR = sys::CT_PR_EV_HWERR;
+    }
+}
+
+impl PrEventSet {
+    /// The events that are fatal by default; see
+    /// ct_pr_tmpl_set_fatal(3CONTRACT).
+    pub const ALLFATAL: PrEventSet = PrEventSet(sys::CT_PR_ALLFATAL);
+}
+
+flag_set! {
+    /// A set of device contract events; see the "Device Contracts" section
+    /// of contract(5).
+    pub struct DevEventSet {
+        const ONLINE = sys::CT_DEV_EV_ONLINE;
+        const DEGRADED = sys::CT_DEV_EV_DEGRADED;
+        const OFFLINE = sys::CT_DEV_EV_OFFLINE;
+    }
+}
+
+flag_set! {
+    /// A set of process contract parameters; see
+    /// ct_pr_tmpl_set_param(3CONTRACT).
+    pub struct PrParamSet {
+        const INHERIT = sys::CT_PR_INHERIT;
+        const NOORPHAN = sys::CT_PR_NOORPHAN;
+        const PGRPONLY = sys::CT_PR_PGRPONLY;
+        const REGENT = sys::CT_PR_REGENT;
+        const KEEP_EXEC = sys::CT_PR_KEEP_EXEC;
+    }
+}
+
+flag_set! {
+    /// Event flags, as returned by ct_event_get_flags(3CONTRACT).
+    pub struct CteFlags {
+        const ACK = sys::CTE_ACK;
+        const INFO = sys::CTE_INFO;
+        const NEG = sys::CTE_NEG;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_operations() {
+        let set = PrEventSet::CORE | PrEventSet::SIGNAL;
+        assert!(set.contains(PrEventSet::CORE));
+        assert!(!set.contains(PrEventSet::CORE | PrEventSet::HWERR));
+        assert!(set.intersects(PrEventSet::ALLFATAL));
+        assert_eq!(set | PrEventSet::HWERR, PrEventSet::ALLFATAL);
+        assert_eq!(PrEventSet::ALLFATAL - set, PrEventSet::HWERR);
+        assert_eq!(set & PrEventSet::SIGNAL, PrEventSet::SIGNAL);
+        assert_eq!(PrEventSet::all().bits(), sys::CT_PR_ALLEVENT);
+        assert_eq!(PrParamSet::all().bits(), sys::CT_PR_ALLPARAM);
+        assert_eq!(DevEventSet::all().bits(), sys::CT_DEV_ALLEVENT);
+
+        let mut set = PrEventSet::empty();
+        assert!(set.is_empty());
+        set.insert(PrEventSet::EXIT);
+        set |= PrEventSet::FORK;
+        set.remove(PrEventSet::EXIT);
+        assert_eq!(set, PrEventSet::FORK);
+    }
+
+    #[test]
+    fn complement_is_of_known_flags() {
+        let unknown = PrEventSet::from_bits_retain(0x1000);
+        assert_eq!(!PrEventSet::empty(), PrEventSet::all());
+        assert_eq!(!(PrEventSet::all() | unknown), PrEventSet::empty());
+        assert_eq!(!CteFlags::INFO, CteFlags::ACK | CteFlags::NEG);
+    }
+
+    #[test]
+    fn unknown_bits() {
+        assert_eq!(PrEventSet::from_bits(0x1001), None);
+        assert_eq!(PrEventSet::from_bits(0x1), Some(PrEventSet::EMPTY));
+        assert_eq!(PrEventSet::from_bits_truncate(0x1001), PrEventSet::EMPTY);
+        assert_eq!(PrEventSet::from_bits_retain(0x1001).bits(), 0x1001);
+        assert_eq!(PrEventSet::from(0x1001).bits(), 0x1001);
+    }
+
+    #[test]
+    fn names_and_debug() {
+        let set = PrEventSet::HWERR | PrEventSet::EMPTY;
+        assert_eq!(set.names().collect::<Vec<_>>(), ["EMPTY", "HWERR"]);
+        assert_eq!(format!("{:?}", set), "PrEventSet(EMPTY | HWERR)");
+        assert_eq!(format!("{:?}", PrParamSet::empty()), "PrParamSet(empty)");
+        assert_eq!(
+            format!("{:?}", CteFlags::from_bits_retain(0x11)),
+            "CteFlags(ACK | 0x10)"
+        );
+        assert_eq!(
+            format!("{:?}", DevEventSet::from_bits_retain(0x100)),
+            "DevEventSet(0x100)"
+        );
+    }
+}
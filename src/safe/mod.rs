@@ -0,0 +1,150 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+//! Safe wrappers around the raw libcontract(3LIB) bindings.
+//!
+//! The types in this module own the file descriptors (as [`OwnedFd`]s) and
+//! handles they are created with, and release them when dropped.  Methods
+//! that operate on file descriptors owned by the caller take a
+//! [`std::os::fd::BorrowedFd`].
+
+use std::ffi::CString;
+use std::fs::OpenOptions;
+use std::os::fd::OwnedFd;
+use std::os::raw::c_int;
+use std::path::Path;
+
+mod adoption;
+#[cfg(feature = "tokio")]
+mod async_event;
+mod command;
+mod contract;
+mod device;
+mod enumerate;
+mod error;
+mod event;
+mod export;
+mod flags;
+mod negotiation;
+#[cfg(feature = "private")]
+mod param;
+mod port;
+mod process;
+mod procfs;
+mod report;
+mod snapshot;
+mod stats;
+mod status;
+mod supervisor;
+mod template;
+#[cfg(feature = "zones")]
+mod zone;
+
+pub use adoption::{AdoptionManager, Recovered, Recovery};
+#[cfg(feature = "tokio")]
+pub use async_event::AsyncEventEndpoint;
+pub use command::{run_in_contract, ContractCommandExt};
+pub use contract::{AbandonGuard, Contract};
+pub use device::{DeviceEvent, DeviceEventKind};
+pub use device::{DeviceState, DeviceStateSet, DeviceTemplateBuilder};
+pub use enumerate::{all_contracts, orphans, AllContracts};
+pub use error::ContractError;
+pub use event::{EndpointKind, Event, EventEndpoint};
+pub use flags::{CteFlags, DevEventSet, PrEventSet, PrParamSet};
+pub use negotiation::{Negotiation, NegotiationState};
+#[cfg(feature = "private")]
+pub use param::{TemplateParam, TemplateParamId};
+pub use port::{EventPortWatcher, PortEvent};
+pub use process::{contract_latest, ProcessTemplateBuilder};
+pub use report::{report_all, ContractReport, DeviceReport, ProcessReport};
+pub use snapshot::{DeviceStatus, ProcessStatus, StatusSnapshot};
+pub use status::{Detail, Status};
+pub use supervisor::{RestartPolicy, Supervisor, SupervisorExit};
+pub use template::{ActiveTemplate, Template};
+
+/*
+ * The libcontract(3LIB) functions that can fail return zero on success, or
+ * an error number on failure.
+ */
+fn check(rv: c_int) -> Result<(), ContractError> {
+    if rv == 0 {
+        Ok(())
+    } else {
+        Err(ContractError::from_errno(rv))
+    }
+}
+
+/*
+ * Strings with interior NUL bytes cannot be passed to libcontract.
+ */
+fn cstring(s: &str) -> Result<CString, ContractError> {
+    CString::new(s).map_err(|_| ContractError::InvalidArgument)
+}
+
+/*
+ * On systems without contracts there is no ctfs to open, so every operation
+ * that would open a contract file fails with ContractError::NotSupported
+ * rather than some less helpful error such as ENOENT.  The simulation in the
+ * "mock" module is supported everywhere.
+ */
+fn supported() -> Result<(), ContractError> {
+    if cfg!(any(libcontract, feature = "mock")) {
+        Ok(())
+    } else {
+        Err(ContractError::NotSupported)
+    }
+}
+
+#[cfg(not(feature = "mock"))]
+fn open(path: &Path, opts: &mut OpenOptions) -> Result<OwnedFd, ContractError> {
+    supported()?;
+    Ok(opts.open(path)?.into())
+}
+
+/*
+ * The simulated files do not distinguish between reading and writing.
+ */
+#[cfg(feature = "mock")]
+fn open(path: &Path, _: &mut OpenOptions) -> Result<OwnedFd, ContractError> {
+    crate::mock::open(path)
+}
+
+fn open_read(path: &Path) -> Result<OwnedFd, ContractError> {
+    open(path, OpenOptions::new().read(true))
+}
+
+fn open_write(path: &Path) -> Result<OwnedFd, ContractError> {
+    open(path, OpenOptions::new().write(true))
+}
+
+fn open_rdwr(path: &Path) -> Result<OwnedFd, ContractError> {
+    open(path, OpenOptions::new().read(true).write(true))
+}
+
+/*
+ * Implements the standard file descriptor traits for a type that owns the
+ * given OwnedFd field.
+ */
+macro_rules! impl_as_fd {
+    ($type_name:ident, $field:ident) => {
+        impl std::os::fd::AsFd for $type_name {
+            fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+                self.$field.as_fd()
+            }
+        }
+
+        impl std::os::fd::AsRawFd for $type_name {
+            fn as_raw_fd(&self) -> std::os::fd::RawFd {
+                self.$field.as_raw_fd()
+            }
+        }
+
+        impl From<$type_name> for std::os::fd::OwnedFd {
+            fn from(v: $type_name) -> std::os::fd::OwnedFd {
+                v.$field
+            }
+        }
+    };
+}
+use impl_as_fd;
@@ -0,0 +1,165 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use super::{Contract, ContractError, DeviceEvent, DeviceEventKind};
+use super::{DeviceState, DeviceTemplateBuilder};
+use crate::ids::{ContractId, EventId};
+
+/// Where a [`Negotiation`] is in the device contract negotiation protocol;
+/// see devices(5).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NegotiationState {
+    /// The proposed transition is waiting for a response.
+    Pending,
+    /// More time to respond has been requested with [`Negotiation::qack`].
+    Queued,
+    /// The transition has been accepted, and the negotiation will end once
+    /// the system has acted on the response.
+    Acked,
+    /// The transition has been refused.
+    Nacked,
+    /// A CT_EV_NEGEND event has been seen for the negotiation.  `newct` is
+    /// the ID of the contract that replaced the original one, or the ID of
+    /// the original contract if it was not replaced.
+    Ended { newct: ContractId },
+}
+
+/// A negotiation over a proposed device state change, started by a critical
+/// device contract event with the CTE_NEG flag set.
+///
+/// The negotiation is answered with [`Negotiation::ack`],
+/// [`Negotiation::nack`] or [`Negotiation::qack`], and ends when the
+/// CT_EV_NEGEND event for it is passed to [`Negotiation::handle`].
+/// Responses that are not allowed in the current state fail with
+/// [`ContractError::InvalidArgument`] without being sent.
+#[derive(Debug)]
+pub struct Negotiation<'a> {
+    ct: &'a Contract,
+    evid: EventId,
+    proposed: DeviceState,
+    state: NegotiationState,
+}
+
+impl<'a> Negotiation<'a> {
+    /// Starts tracking the negotiation begun by `ev`, which must be a
+    /// negotiation event for the contract `ct`.
+    pub fn start(
+        ct: &'a Contract,
+        ev: &DeviceEvent,
+    ) -> Result<Negotiation<'a>, ContractError> {
+        let proposed = match ev.kind {
+            DeviceEventKind::Transition(state) if ev.is_negotiation() => state,
+            _ => return Err(ContractError::InvalidArgument),
+        };
+        if ev.ctid != ct.id() {
+            return Err(ContractError::InvalidArgument);
+        }
+
+        Ok(Negotiation {
+            ct,
+            evid: ev.evid,
+            proposed,
+            state: NegotiationState::Pending,
+        })
+    }
+
+    /// Returns the ID of the event that started the negotiation.
+    pub fn evid(&self) -> EventId {
+        self.evid
+    }
+
+    /// Returns the state the device is proposing to move to.
+    pub fn proposed(&self) -> DeviceState {
+        self.proposed
+    }
+
+    pub fn state(&self) -> NegotiationState {
+        self.state
+    }
+
+    pub fn is_ended(&self) -> bool {
+        matches!(self.state, NegotiationState::Ended { .. })
+    }
+
+    /// Accepts the proposed transition.
+    pub fn ack(&mut self) -> Result<(), ContractError> {
+        self.awaiting_response()?;
+        self.ct.ack(self.evid)?;
+        self.state = NegotiationState::Acked;
+        Ok(())
+    }
+
+    /// Accepts the proposed transition, asking that a new contract with the
+    /// terms in `config` replace this one when the negotiation ends; see
+    /// ct_ctl_newct(3CONTRACT).  The terms would typically cover the same
+    /// minor node, with an acceptable state set that includes the proposed
+    /// state.  Once the negotiation has ended, the new contract can be
+    /// opened with [`Negotiation::replacement`].
+    ///
+    /// The replacement is created from `config`, which must describe a
+    /// device contract; see ct_ctl_ack(3CONTRACT).
+    pub fn ack_replacing(
+        &mut self,
+        config: &DeviceTemplateBuilder,
+    ) -> Result<(), ContractError> {
+        self.awaiting_response()?;
+        let tmpl = config.build()?;
+        self.ct.newct(self.evid, &tmpl)?;
+        self.ct.ack(self.evid)?;
+        self.state = NegotiationState::Acked;
+        Ok(())
+    }
+
+    /// Refuses the proposed transition.
+    pub fn nack(&mut self) -> Result<(), ContractError> {
+        self.awaiting_response()?;
+        self.ct.nack(self.evid)?;
+        self.state = NegotiationState::Nacked;
+        Ok(())
+    }
+
+    /// Requests more time before responding; an ack or nack must still
+    /// follow.
+    pub fn qack(&mut self) -> Result<(), ContractError> {
+        self.awaiting_response()?;
+        self.ct.qack(self.evid)?;
+        self.state = NegotiationState::Queued;
+        Ok(())
+    }
+
+    /// Feeds an event from the contract to the negotiation.  Returns true if
+    /// the event ended this negotiation, and false if it is unrelated.
+    pub fn handle(&mut self, ev: &DeviceEvent) -> bool {
+        match ev.kind {
+            DeviceEventKind::NegEnd { nevid, newct }
+                if nevid == self.evid && !self.is_ended() =>
+            {
+                self.state = NegotiationState::Ended { newct };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Once the negotiation has ended, opens the contract that replaced the
+    /// original one.  Returns `None` if the negotiation has not ended, or if
+    /// the contract was not replaced.  The caller is expected to adopt the
+    /// replacement, if need be, and abandon the original contract.
+    pub fn replacement(&self) -> Result<Option<Contract>, ContractError> {
+        match self.state {
+            NegotiationState::Ended { newct } if newct != self.ct.id() => {
+                Ok(Some(Contract::open(newct)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn awaiting_response(&self) -> Result<(), ContractError> {
+        match self.state {
+            NegotiationState::Pending | NegotiationState::Queued => Ok(()),
+            _ => Err(ContractError::InvalidArgument),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::raw::{c_int, c_uint, c_void};
+
+use super::{ContractError, PrEventSet, PrParamSet, Template};
+use crate as sys;
+use crate::ct_param_t;
+use crate::ctfs_ioctl::{CT_TGET, CT_TSET};
+
+/// The identifier of a template term that can be read or written directly
+/// with the CT_TGET and CT_TSET ioctls; see [`Template::tmpl_param`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemplateParamId {
+    Cookie,
+    Critical,
+    Informative,
+    Fatal,
+    Params,
+    SvcFmri,
+    SvcAux,
+}
+
+impl TemplateParamId {
+    /// Returns the value used for the identifier in the `ctpm_id` member of
+    /// a [`ct_param_t`], e.g., [`sys::CTP_COOKIE`].
+    pub const fn raw(self) -> u32 {
+        match self {
+            TemplateParamId::Cookie => sys::CTP_COOKIE,
+            TemplateParamId::Critical => sys::CTP_EV_CRITICAL,
+            TemplateParamId::Informative => sys::CTP_EV_INFO,
+            TemplateParamId::Fatal => sys::CTPP_EV_FATAL,
+            TemplateParamId::Params => sys::CTPP_PARAMS,
+            TemplateParamId::SvcFmri => sys::CTPP_SVC_FMRI,
+            TemplateParamId::SvcAux => sys::CTPP_CREATOR_AUX,
+        }
+    }
+}
+
+/// A template term with its value, as passed to the CT_TSET ioctl by
+/// [`Template::set_tmpl_param`].  The event sets of [`TemplateParam::Critical`]
+/// and [`TemplateParam::Informative`] are those of the template's contract
+/// type; the other terms apply to process contract templates only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateParam {
+    Cookie(u64),
+    Critical(c_uint),
+    Informative(c_uint),
+    Fatal(PrEventSet),
+    Params(PrParamSet),
+    SvcFmri(CString),
+    SvcAux(CString),
+}
+
+impl TemplateParam {
+    pub fn id(&self) -> TemplateParamId {
+        match self {
+            TemplateParam::Cookie(_) => TemplateParamId::Cookie,
+            TemplateParam::Critical(_) => TemplateParamId::Critical,
+            TemplateParam::Informative(_) => TemplateParamId::Informative,
+            TemplateParam::Fatal(_) => TemplateParamId::Fatal,
+            TemplateParam::Params(_) => TemplateParamId::Params,
+            TemplateParam::SvcFmri(_) => TemplateParamId::SvcFmri,
+            TemplateParam::SvcAux(_) => TemplateParamId::SvcAux,
+        }
+    }
+}
+
+/*
+ * Template terms are read and written directly with ioctls on the template
+ * file, in the same way that libcontract does internally.  Numeric values are
+ * always passed as a uint64_t, and strings with their terminating NUL.
+ */
+impl Template {
+    /// Sets a template term with the CT_TSET ioctl.  Values larger than
+    /// [`sys::CT_PARAM_MAX_SIZE`] are rejected with
+    /// [`ContractError::InvalidArgument`] before the ioctl is issued, as the
+    /// system would reject them.
+    pub fn set_tmpl_param(
+        &self,
+        param: &TemplateParam,
+    ) -> Result<(), ContractError> {
+        let id = param.id().raw();
+        let mut n: u64 = match param {
+            TemplateParam::Cookie(cookie) => *cookie,
+            TemplateParam::Critical(events)
+            | TemplateParam::Informative(events) => (*events).into(),
+            TemplateParam::Fatal(events) => events.bits().into(),
+            TemplateParam::Params(params) => params.bits().into(),
+            TemplateParam::SvcFmri(s) | TemplateParam::SvcAux(s) => {
+                let bytes = s.as_bytes_with_nul();
+                let value = bytes.as_ptr().cast_mut().cast();
+                self.tmpl_ioctl(CT_TSET, id, value, bytes.len())?;
+                return Ok(());
+            }
+        };
+
+        self.tmpl_ioctl(CT_TSET, id, (&mut n as *mut u64).cast(), 8)?;
+        Ok(())
+    }
+
+    /// Reads a template term with the CT_TGET ioctl.
+    pub fn tmpl_param(
+        &self,
+        id: TemplateParamId,
+    ) -> Result<TemplateParam, ContractError> {
+        /*
+         * Event sets and parameters are all 32 bits wide.
+         */
+        let bits = || self.tmpl_u64(id).map(|n| n as c_uint);
+
+        Ok(match id {
+            TemplateParamId::Cookie => {
+                TemplateParam::Cookie(self.tmpl_u64(id)?)
+            }
+            TemplateParamId::Critical => TemplateParam::Critical(bits()?),
+            TemplateParamId::Informative => TemplateParam::Informative(bits()?),
+            TemplateParamId::Fatal => {
+                TemplateParam::Fatal(PrEventSet::from_bits_retain(bits()?))
+            }
+            TemplateParamId::Params => {
+                TemplateParam::Params(PrParamSet::from_bits_retain(bits()?))
+            }
+            TemplateParamId::SvcFmri => {
+                TemplateParam::SvcFmri(self.tmpl_cstring(id)?)
+            }
+            TemplateParamId::SvcAux => {
+                TemplateParam::SvcAux(self.tmpl_cstring(id)?)
+            }
+        })
+    }
+
+    fn tmpl_u64(&self, id: TemplateParamId) -> Result<u64, ContractError> {
+        let mut n: u64 = 0;
+        self.tmpl_ioctl(CT_TGET, id.raw(), (&mut n as *mut u64).cast(), 8)?;
+        Ok(n)
+    }
+
+    fn tmpl_cstring(
+        &self,
+        id: TemplateParamId,
+    ) -> Result<CString, ContractError> {
+        let mut buf = vec![0u8; sys::CT_PARAM_MAX_SIZE];
+        let value = buf.as_mut_ptr().cast();
+        let size = self.tmpl_ioctl(CT_TGET, id.raw(), value, buf.len())?;
+        buf.truncate(size);
+
+        Ok(CStr::from_bytes_until_nul(&buf)
+            .map_err(|_| ContractError::Overflow)?
+            .to_owned())
+    }
+
+    /*
+     * Issues a template ioctl for the parameter with the given value buffer,
+     * returning the size the system stored back into the ct_param_t.
+     */
+    fn tmpl_ioctl(
+        &self,
+        request: c_int,
+        id: u32,
+        value: *mut c_void,
+        size: usize,
+    ) -> Result<usize, ContractError> {
+        if size > sys::CT_PARAM_MAX_SIZE {
+            return Err(ContractError::InvalidArgument);
+        }
+
+        let mut param = ct_param_t {
+            ctpm_id: id,
+            ctpm_size: size as u32,
+            ctpm_value: value,
+        };
+        let rv =
+            unsafe { libc::ioctl(self.as_raw_fd(), request as _, &mut param) };
+        if rv == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(param.ctpm_size as usize)
+    }
+}
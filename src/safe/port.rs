@@ -0,0 +1,296 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::os::raw::c_short;
+use std::time::Duration;
+
+use super::{impl_as_fd, ContractError, Event, EventEndpoint};
+
+/*
+ * The maximum number of port events retrieved by a single call to
+ * port_getn(3C).
+ */
+const MAX_PORT_EVENTS: usize = 64;
+
+/// Something that happened on an [`EventPortWatcher`].
+#[derive(Debug)]
+pub enum PortEvent {
+    /// An event was read from the watched endpoint with the given file
+    /// descriptor.
+    Contract { endpoint: RawFd, event: Event },
+    /// Reading from the watched endpoint with the given file descriptor
+    /// failed.  The endpoint is no longer associated with the port.
+    Error { endpoint: RawFd, error: ContractError },
+    /// A file descriptor added with [`EventPortWatcher::associate_fd`]
+    /// became ready; `events` are the poll(2) events that occurred.
+    Fd { fd: RawFd, events: c_short },
+}
+
+/// Waits for events on many contract event endpoints, and other file
+/// descriptors, using an illumos event port; see port_create(3C).
+#[derive(Debug)]
+pub struct EventPortWatcher {
+    port: OwnedFd,
+    endpoints: HashMap<RawFd, EventEndpoint>,
+}
+
+impl EventPortWatcher {
+    pub fn new() -> Result<EventPortWatcher, ContractError> {
+        Ok(EventPortWatcher {
+            port: evport::create()?,
+            endpoints: HashMap::new(),
+        })
+    }
+
+    /// Starts watching an event endpoint, which is put in non-blocking mode.
+    /// Returns the file descriptor of the endpoint, which identifies it in
+    /// [`PortEvent`]s and to [`EventPortWatcher::remove`].
+    pub fn add(&mut self, ep: EventEndpoint) -> Result<RawFd, ContractError> {
+        let fd = ep.as_raw_fd();
+
+        ep.set_nonblocking(true)?;
+        self.associate(fd, libc::POLLIN)?;
+        self.endpoints.insert(fd, ep);
+
+        Ok(fd)
+    }
+
+    /// Stops watching an event endpoint, returning it to the caller.
+    pub fn remove(&mut self, endpoint: RawFd) -> Option<EventEndpoint> {
+        let ep = self.endpoints.remove(&endpoint)?;
+
+        /*
+         * The endpoint may not be associated if its last read failed, so an
+         * error here is of no consequence.
+         */
+        let _ = evport::dissociate(self.port.as_raw_fd(), endpoint);
+
+        Some(ep)
+    }
+
+    /// Associates some other file descriptor with the port, so that
+    /// [`EventPortWatcher::wait`] reports when it is ready.  As with all
+    /// event port file descriptor associations, this is one-shot: the
+    /// descriptor must be associated again after each event is reported.
+    pub fn associate_fd(
+        &self,
+        fd: BorrowedFd<'_>,
+        events: c_short,
+    ) -> Result<(), ContractError> {
+        if self.endpoints.contains_key(&fd.as_raw_fd()) {
+            return Err(ContractError::InvalidArgument);
+        }
+
+        self.associate(fd.as_raw_fd(), events)
+    }
+
+    /// Returns the number of event endpoints being watched.
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Waits until at least one watched descriptor is ready, or the timeout
+    /// expires, and returns what happened.  All events available on each
+    /// ready endpoint are read before it is associated with the port again.
+    /// An empty list is returned if the timeout expires.
+    pub fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<PortEvent>, ContractError> {
+        let ready = evport::getn(self.port.as_raw_fd(), timeout)?;
+
+        let mut out = Vec::new();
+        for (fd, events) in ready {
+            let Some(ep) = self.endpoints.get(&fd) else {
+                out.push(PortEvent::Fd { fd, events });
+                continue;
+            };
+
+            let error = loop {
+                match ep.read() {
+                    Ok(event) => {
+                        out.push(PortEvent::Contract { endpoint: fd, event })
+                    }
+                    Err(ContractError::WouldBlock) => break None,
+                    Err(e) => break Some(e),
+                }
+            };
+
+            /*
+             * Associations are one-shot, so the endpoint must be associated
+             * again to hear about further events.
+             */
+            let res = match error {
+                Some(e) => Err(e),
+                None => self.associate(fd, libc::POLLIN),
+            };
+            if let Err(error) = res {
+                out.push(PortEvent::Error { endpoint: fd, error });
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn associate(
+        &self,
+        fd: RawFd,
+        events: c_short,
+    ) -> Result<(), ContractError> {
+        evport::associate(self.port.as_raw_fd(), fd, events)
+    }
+}
+
+impl_as_fd!(EventPortWatcher, port);
+
+/*
+ * The event port functions used by EventPortWatcher; see port_create(3C).
+ * Only file descriptor associations are used.
+ */
+#[cfg(libcontract)]
+mod evport {
+    use std::io;
+    use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+    use std::os::raw::{c_int, c_short, c_uint};
+    use std::ptr;
+    use std::time::Duration;
+
+    use super::MAX_PORT_EVENTS;
+    use crate::safe::ContractError;
+
+    pub(super) fn create() -> Result<OwnedFd, ContractError> {
+        let port = unsafe { libc::port_create() };
+        if port < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(unsafe { OwnedFd::from_raw_fd(port) })
+    }
+
+    pub(super) fn associate(
+        port: RawFd,
+        fd: RawFd,
+        events: c_short,
+    ) -> Result<(), ContractError> {
+        let rv = unsafe {
+            libc::port_associate(
+                port,
+                libc::PORT_SOURCE_FD,
+                fd as libc::uintptr_t,
+                events as c_int,
+                ptr::null_mut(),
+            )
+        };
+        if rv < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn dissociate(
+        port: RawFd,
+        fd: RawFd,
+    ) -> Result<(), ContractError> {
+        let rv = unsafe {
+            libc::port_dissociate(
+                port,
+                libc::PORT_SOURCE_FD,
+                fd as libc::uintptr_t,
+            )
+        };
+        if rv < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(())
+    }
+
+    /*
+     * Returns the file descriptors that became ready, and the poll(2) events
+     * that occurred for each.  The list is empty if the timeout expired.
+     */
+    pub(super) fn getn(
+        port: RawFd,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(RawFd, c_short)>, ContractError> {
+        let mut pevs: Vec<libc::port_event> =
+            Vec::with_capacity(MAX_PORT_EVENTS);
+        let mut ts = timeout.map(|t| libc::timespec {
+            tv_sec: t.as_secs() as libc::time_t,
+            tv_nsec: t.subsec_nanos() as libc::c_long,
+        });
+        let tsp = match &mut ts {
+            Some(ts) => ts as *mut libc::timespec,
+            None => ptr::null_mut(),
+        };
+
+        let mut nget: c_uint = 1;
+        let rv = unsafe {
+            libc::port_getn(
+                port,
+                pevs.as_mut_ptr(),
+                MAX_PORT_EVENTS as c_uint,
+                &mut nget,
+                tsp,
+            )
+        };
+        if rv < 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::ETIME) {
+                return Err(e.into());
+            }
+        }
+        unsafe { pevs.set_len(nget as usize) };
+
+        Ok(pevs
+            .into_iter()
+            .filter(|pev| pev.portev_source == libc::PORT_SOURCE_FD as u16)
+            .map(|pev| {
+                (pev.portev_object as RawFd, pev.portev_events as c_short)
+            })
+            .collect())
+    }
+}
+
+/*
+ * Elsewhere, there are no event ports, and so no watcher can be created.
+ */
+#[cfg(not(libcontract))]
+mod evport {
+    use std::os::fd::{OwnedFd, RawFd};
+    use std::os::raw::c_short;
+    use std::time::Duration;
+
+    use crate::safe::ContractError;
+
+    pub(super) fn create() -> Result<OwnedFd, ContractError> {
+        Err(ContractError::NotSupported)
+    }
+
+    pub(super) fn associate(
+        _: RawFd,
+        _: RawFd,
+        _: c_short,
+    ) -> Result<(), ContractError> {
+        Err(ContractError::NotSupported)
+    }
+
+    pub(super) fn dissociate(_: RawFd, _: RawFd) -> Result<(), ContractError> {
+        Err(ContractError::NotSupported)
+    }
+
+    pub(super) fn getn(
+        _: RawFd,
+        _: Option<Duration>,
+    ) -> Result<Vec<(RawFd, c_short)>, ContractError> {
+        Err(ContractError::NotSupported)
+    }
+}
@@ -0,0 +1,493 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::ffi::CStr;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use std::os::raw::{c_char, c_int, c_uint};
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use libc::pid_t;
+
+use super::status::status_str;
+use super::{check, cstring, open_read, Contract, ContractError};
+use super::{Detail, PrEventSet, PrParamSet, Status, Template};
+use crate as sys;
+use crate::ctfs;
+use crate::ctstate_t;
+use crate::ids::ContractId;
+
+/*
+ * The initial buffer size used to read the service strings of a template,
+ * which is enough for any reasonable FMRI.
+ */
+const TMPL_STRING_SIZE: usize = 1024;
+
+/// Returns the control endpoint and an open status endpoint for the process
+/// contract most recently created by the calling thread, e.g., by forking
+/// a child while a template was active.
+///
+/// The status endpoint may be passed to [`Status::read`] to observe the new
+/// contract without reopening it.
+pub fn contract_latest() -> Result<(Contract, OwnedFd), ContractError> {
+    let ctid = latest_id()?;
+    let ct = Contract::open(ctid)?;
+    let status = open_read(&ctfs::status_path(ctid.as_raw()))?;
+    Ok((ct, status))
+}
+
+/*
+ * Returns the ID of the process contract most recently created by the
+ * calling thread.
+ */
+pub(super) fn latest_id() -> Result<ContractId, ContractError> {
+    let latest = open_read(Path::new(ctfs::PROCESS_LATEST))?;
+    Ok(Status::read(latest.as_fd(), Detail::Common)?.id())
+}
+
+/*
+ * Process contract status; see ct_pr_status_get_param(3CONTRACT).
+ */
+impl Status {
+    /// Returns the IDs of the processes in a process contract.  The status
+    /// must have been read with [`Detail::All`]; otherwise this fails with
+    /// [`ContractError::InvalidArgument`].
+    pub fn members(&self) -> Result<&[pid_t], ContractError> {
+        self.require(Detail::All)?;
+
+        let mut pids = ptr::null_mut();
+        let mut n = 0;
+        check(unsafe {
+            sys::ct_pr_status_get_members(self.as_ptr(), &mut pids, &mut n)
+        })?;
+        Ok(unsafe { status_slice(pids, n) })
+    }
+
+    /// Returns the IDs of the contracts inherited by a process contract.
+    /// As with [`Status::members`], this requires [`Detail::All`].
+    pub fn contracts(&self) -> Result<&[ContractId], ContractError> {
+        self.require(Detail::All)?;
+
+        let mut ctids = ptr::null_mut();
+        let mut n = 0;
+        check(unsafe {
+            sys::ct_pr_status_get_contracts(self.as_ptr(), &mut ctids, &mut n)
+        })?;
+        /*
+         * ContractId is a transparent wrapper around ctid_t, so the array
+         * can be viewed as one of IDs.
+         */
+        Ok(unsafe { status_slice(ctids.cast::<ContractId>(), n) })
+    }
+
+    /// Returns the FMRI of the service that created the contract.  This and
+    /// the other service accessors require at least [`Detail::Fixed`].
+    pub fn svc_fmri(&self) -> Result<&CStr, ContractError> {
+        self.require(Detail::Fixed)?;
+        status_str(|p| unsafe {
+            sys::ct_pr_status_get_svc_fmri(self.as_ptr(), p)
+        })
+    }
+
+    /// Returns the creator-defined auxiliary string for the service.
+    pub fn svc_aux(&self) -> Result<&CStr, ContractError> {
+        self.require(Detail::Fixed)?;
+        status_str(|p| unsafe {
+            sys::ct_pr_status_get_svc_aux(self.as_ptr(), p)
+        })
+    }
+
+    /// Returns the name of the process that set the service FMRI.
+    pub fn svc_creator(&self) -> Result<&CStr, ContractError> {
+        self.require(Detail::Fixed)?;
+        status_str(|p| unsafe {
+            sys::ct_pr_status_get_svc_creator(self.as_ptr(), p)
+        })
+    }
+
+    /// Returns the ID of the contract that set the service FMRI.
+    pub fn svc_ctid(&self) -> Result<ContractId, ContractError> {
+        self.require(Detail::Fixed)?;
+        let mut ctid = 0;
+        check(unsafe {
+            sys::ct_pr_status_get_svc_ctid(self.as_ptr(), &mut ctid)
+        })?;
+        Ok(ContractId(ctid))
+    }
+}
+
+/*
+ * Checks the subject of a transfer as the system does when the term is set;
+ * see contract_process_tmpl_set() in the kernel.  The contract may still have
+ * changed by the time the term is set, in which case the system's own error
+ * is reported.
+ */
+fn check_transfer(ctid: ContractId) -> Result<(), ContractError> {
+    let st = match Status::read_ctid(ctid, Detail::All) {
+        Ok(st) => st,
+        Err(ContractError::NotFound) => {
+            return Err(ContractError::NoSuchContract)
+        }
+        Err(e) => return Err(e),
+    };
+
+    check_subject(st.type_name(), st.state(), st.holder(), || st.members())
+}
+
+/*
+ * Checks the status of the subject of a transfer.  The members are only read
+ * once the contract is known to be a process contract.
+ */
+fn check_subject<'a>(
+    type_name: &CStr,
+    state: ctstate_t,
+    holder: libc::id_t,
+    members: impl FnOnce() -> Result<&'a [pid_t], ContractError>,
+) -> Result<(), ContractError> {
+    if type_name.to_bytes() != ctfs::PROCESS_TYPE.as_bytes() {
+        return Err(ContractError::NotProcessContract);
+    }
+    if state != ctstate_t::CTS_OWNED
+        || holder != std::process::id() as libc::id_t
+    {
+        return Err(ContractError::NotHeld);
+    }
+    if !members()?.is_empty() {
+        return Err(ContractError::NotEmpty);
+    }
+
+    Ok(())
+}
+
+/*
+ * Arrays returned by the status accessors point into the status handle, and
+ * so live as long as it does.  An empty array may be returned as NULL.
+ */
+unsafe fn status_slice<'a, T>(p: *const T, n: c_uint) -> &'a [T] {
+    if p.is_null() || n == 0 {
+        return &[];
+    }
+
+    slice::from_raw_parts(p, n as usize)
+}
+
+/*
+ * Unlike most libcontract functions, ct_pr_tmpl_get_svc_fmri(3CONTRACT) and
+ * ct_pr_tmpl_get_svc_aux(3CONTRACT) return the length of the string, which
+ * may be larger than the buffer, or -1 with errno set on failure.
+ */
+fn tmpl_string<F>(get: F) -> Result<String, ContractError>
+where
+    F: Fn(*mut c_char, libc::size_t) -> c_int,
+{
+    let mut buf = vec![0u8; TMPL_STRING_SIZE];
+
+    loop {
+        let rv = get(buf.as_mut_ptr() as *mut c_char, buf.len());
+        if rv < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        let len = rv as usize;
+        if len >= buf.len() {
+            buf.resize(len + 1, 0);
+            continue;
+        }
+
+        let s = CStr::from_bytes_until_nul(&buf)
+            .map_err(|_| ContractError::Overflow)?;
+        return Ok(s.to_string_lossy().into_owned());
+    }
+}
+
+/*
+ * Process contract template terms; see ct_pr_tmpl_set_transfer(3CONTRACT).
+ */
+impl Template {
+    pub fn set_fatal(&self, events: PrEventSet) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_pr_tmpl_set_fatal(self.as_raw_fd(), events.bits())
+        })
+    }
+
+    pub fn set_param(&self, params: PrParamSet) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_pr_tmpl_set_param(self.as_raw_fd(), params.bits())
+        })
+    }
+
+    /// Sets the contract whose inherited contracts are transferred to the
+    /// new contract when it is created, as a restarter does when it
+    /// replaces a contract it held; an ID of 0 clears the term.
+    ///
+    /// The contract is checked before the term is set, so that the reason
+    /// the system would reject it is reported precisely: an unknown
+    /// contract fails with [`ContractError::NoSuchContract`], and otherwise
+    /// the contract must be a process contract
+    /// ([`ContractError::NotProcessContract`]), held by the caller
+    /// ([`ContractError::NotHeld`]), and have no members left
+    /// ([`ContractError::NotEmpty`]).
+    pub fn set_transfer(&self, ctid: ContractId) -> Result<(), ContractError> {
+        if ctid.as_raw() != 0 {
+            check_transfer(ctid)?;
+        }
+
+        match check(unsafe {
+            sys::ct_pr_tmpl_set_transfer(self.as_raw_fd(), ctid.as_raw())
+        }) {
+            Err(ContractError::AccessDenied) => Err(ContractError::NotHeld),
+            Err(e) if e.errno() == Some(libc::ENOTEMPTY) => {
+                Err(ContractError::NotEmpty)
+            }
+            res => res,
+        }
+    }
+
+    pub fn set_svc_fmri(&self, fmri: &CStr) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_pr_tmpl_set_svc_fmri(self.as_raw_fd(), fmri.as_ptr())
+        })
+    }
+
+    pub fn set_svc_aux(&self, aux: &CStr) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_pr_tmpl_set_svc_aux(self.as_raw_fd(), aux.as_ptr())
+        })
+    }
+
+    pub fn svc_fmri(&self) -> Result<String, ContractError> {
+        tmpl_string(|buf, size| unsafe {
+            sys::ct_pr_tmpl_get_svc_fmri(self.as_raw_fd(), buf, size)
+        })
+    }
+
+    pub fn svc_aux(&self) -> Result<String, ContractError> {
+        tmpl_string(|buf, size| unsafe {
+            sys::ct_pr_tmpl_get_svc_aux(self.as_raw_fd(), buf, size)
+        })
+    }
+
+    pub fn fatal(&self) -> Result<PrEventSet, ContractError> {
+        let mut events = 0;
+        check(unsafe {
+            sys::ct_pr_tmpl_get_fatal(self.as_raw_fd(), &mut events)
+        })?;
+        Ok(PrEventSet::from_bits_retain(events))
+    }
+
+    pub fn param(&self) -> Result<PrParamSet, ContractError> {
+        let mut params = 0;
+        check(unsafe {
+            sys::ct_pr_tmpl_get_param(self.as_raw_fd(), &mut params)
+        })?;
+        Ok(PrParamSet::from_bits_retain(params))
+    }
+
+    pub fn transfer(&self) -> Result<ContractId, ContractError> {
+        let mut ctid = 0;
+        check(unsafe {
+            sys::ct_pr_tmpl_get_transfer(self.as_raw_fd(), &mut ctid)
+        })?;
+        Ok(ContractId(ctid))
+    }
+}
+
+/// Configures a process contract template.  Terms that are not set keep the
+/// defaults described in process(5).
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTemplateBuilder {
+    critical: Option<PrEventSet>,
+    informative: Option<PrEventSet>,
+    fatal: Option<PrEventSet>,
+    params: Option<PrParamSet>,
+    cookie: Option<u64>,
+    svc_fmri: Option<String>,
+    svc_aux: Option<String>,
+}
+
+impl ProcessTemplateBuilder {
+    pub fn new() -> ProcessTemplateBuilder {
+        Default::default()
+    }
+
+    pub fn critical(mut self, events: PrEventSet) -> ProcessTemplateBuilder {
+        self.critical = Some(events);
+        self
+    }
+
+    pub fn informative(mut self, events: PrEventSet) -> ProcessTemplateBuilder {
+        self.informative = Some(events);
+        self
+    }
+
+    /// Sets the fatal event set: the events that cause all processes in
+    /// the contract, or its process group, to be killed.
+    pub fn fatal(mut self, events: PrEventSet) -> ProcessTemplateBuilder {
+        self.fatal = Some(events);
+        self
+    }
+
+    pub fn params(mut self, params: PrParamSet) -> ProcessTemplateBuilder {
+        self.params = Some(params);
+        self
+    }
+
+    pub fn cookie(mut self, cookie: u64) -> ProcessTemplateBuilder {
+        self.cookie = Some(cookie);
+        self
+    }
+
+    pub fn svc_fmri<S: Into<String>>(
+        mut self,
+        fmri: S,
+    ) -> ProcessTemplateBuilder {
+        self.svc_fmri = Some(fmri.into());
+        self
+    }
+
+    pub fn svc_aux<S: Into<String>>(
+        mut self,
+        aux: S,
+    ) -> ProcessTemplateBuilder {
+        self.svc_aux = Some(aux.into());
+        self
+    }
+
+    /// Configures the terms that svc.startd(8) gives the contracts of
+    /// service methods, if the calling process is running under SMF, i.e.,
+    /// if SMF_FMRI is set in its environment; otherwise, this returns the
+    /// builder unchanged.  This is the setup needed by delegated restarters,
+    /// which must create the contracts of the services they manage:
+    ///
+    /// - the service FMRI is taken from SMF_FMRI, and the auxiliary string
+    ///   from SMF_METHOD, if set;
+    /// - EMPTY, CORE, SIGNAL and HWERR events are critical, and no events
+    ///   are informative;
+    /// - only HWERR is fatal;
+    /// - the contract is inherited by the regent, as with
+    ///   [`PrParamSet::INHERIT`] and [`PrParamSet::REGENT`].
+    ///
+    /// Terms set after this call override those set by it.  Setting the
+    /// service FMRI requires the {PRIV_CONTRACT_IDENTITY} privilege.
+    pub fn smf_method(self) -> ProcessTemplateBuilder {
+        let Some(fmri) = env_string(SMF_FMRI) else {
+            return self;
+        };
+
+        let mut builder = self
+            .svc_fmri(fmri)
+            .critical(
+                PrEventSet::EMPTY
+                    | PrEventSet::CORE
+                    | PrEventSet::SIGNAL
+                    | PrEventSet::HWERR,
+            )
+            .informative(PrEventSet::empty())
+            .fatal(PrEventSet::HWERR)
+            .params(PrParamSet::INHERIT | PrParamSet::REGENT);
+        if let Some(method) = env_string(SMF_METHOD) {
+            builder = builder.svc_aux(method);
+        }
+        builder
+    }
+
+    /// Opens a new process contract template and applies the configured
+    /// terms to it.
+    pub fn build(&self) -> Result<Template, ContractError> {
+        let tmpl = Template::open(ctfs::PROCESS_TEMPLATE)?;
+
+        if let Some(events) = self.critical {
+            tmpl.set_critical(events)?;
+        }
+        if let Some(events) = self.informative {
+            tmpl.set_informative(events)?;
+        }
+        if let Some(events) = self.fatal {
+            tmpl.set_fatal(events)?;
+        }
+        if let Some(params) = self.params {
+            tmpl.set_param(params)?;
+        }
+        if let Some(cookie) = self.cookie {
+            tmpl.set_cookie(cookie)?;
+        }
+        if let Some(fmri) = &self.svc_fmri {
+            tmpl.set_svc_fmri(&cstring(fmri)?)?;
+        }
+        if let Some(aux) = &self.svc_aux {
+            tmpl.set_svc_aux(&cstring(aux)?)?;
+        }
+
+        Ok(tmpl)
+    }
+
+    /// Builds the template and makes it the active process contract template
+    /// for the calling thread.
+    pub fn activate(&self) -> Result<Template, ContractError> {
+        let tmpl = self.build()?;
+        tmpl.activate()?;
+        Ok(tmpl)
+    }
+
+    /// Builds the template and creates a new contract from it, returning the
+    /// ID of the new contract.
+    pub fn create(&self) -> Result<ContractId, ContractError> {
+        self.build()?.create()
+    }
+}
+
+/*
+ * The environment variables set by svc.startd(8) for service methods; see
+ * smf_method(7).
+ */
+const SMF_FMRI: &str = "SMF_FMRI";
+const SMF_METHOD: &str = "SMF_METHOD";
+
+/*
+ * Variables that are set but empty, or are not valid UTF-8, are ignored.
+ */
+fn env_string(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subject(
+        type_name: &CStr,
+        state: ctstate_t,
+        holder: libc::id_t,
+        members: &[pid_t],
+    ) -> Result<(), ContractError> {
+        check_subject(type_name, state, holder, || Ok(members))
+    }
+
+    #[test]
+    fn transfer_subject() {
+        let us = std::process::id() as libc::id_t;
+
+        assert!(subject(c"process", ctstate_t::CTS_OWNED, us, &[]).is_ok());
+        assert!(matches!(
+            check_subject(c"device", ctstate_t::CTS_OWNED, us, || {
+                panic!("members read for a device contract")
+            }),
+            Err(ContractError::NotProcessContract)
+        ));
+        assert!(matches!(
+            subject(c"process", ctstate_t::CTS_OWNED, us + 1, &[]),
+            Err(ContractError::NotHeld)
+        ));
+        assert!(matches!(
+            subject(c"process", ctstate_t::CTS_ORPHAN, us, &[]),
+            Err(ContractError::NotHeld)
+        ));
+        assert!(matches!(
+            subject(c"process", ctstate_t::CTS_OWNED, us, &[1234]),
+            Err(ContractError::NotEmpty)
+        ));
+    }
+}
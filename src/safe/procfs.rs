@@ -0,0 +1,110 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::path::Path;
+
+use libc::pid_t;
+
+use super::ContractError;
+use crate::ids::ContractId;
+
+/*
+ * The leading members of psinfo_t, from <sys/procfs.h>, up to and including
+ * pr_contract.  Only these are read from a psinfo file; the rest of the
+ * structure, including the representative lwpsinfo_t, is not needed.
+ */
+#[cfg(not(feature = "mock"))]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct psinfo_head {
+    pr_flag: libc::c_int,
+    pr_nlwp: libc::c_int,
+    pr_pid: libc::pid_t,
+    pr_ppid: libc::pid_t,
+    pr_pgid: libc::pid_t,
+    pr_sid: libc::pid_t,
+    pr_uid: libc::uid_t,
+    pr_euid: libc::uid_t,
+    pr_gid: libc::gid_t,
+    pr_egid: libc::gid_t,
+    pr_addr: libc::uintptr_t,
+    pr_size: libc::size_t,
+    pr_rssize: libc::size_t,
+    pr_pad1: libc::size_t,
+    pr_ttydev: libc::dev_t,
+    pr_pctcpu: libc::c_ushort,
+    pr_pctmem: libc::c_ushort,
+    pr_start: libc::timespec,
+    pr_time: libc::timespec,
+    pr_ctime: libc::timespec,
+    pr_fname: [libc::c_char; 16],
+    pr_psargs: [libc::c_char; 80],
+    pr_wstat: libc::c_int,
+    pr_argc: libc::c_int,
+    pr_argv: libc::uintptr_t,
+    pr_envp: libc::uintptr_t,
+    pr_dmodel: libc::c_char,
+    pr_pad2: [libc::c_char; 3],
+    pr_taskid: libc::id_t,
+    pr_projid: libc::id_t,
+    pr_nzomb: libc::c_int,
+    pr_poolid: libc::id_t,
+    pr_zoneid: crate::zoneid_t,
+    pr_contract: libc::id_t,
+}
+
+/*
+ * The offset of pr_contract is fixed by the ABI for 64-bit processes.
+ */
+#[cfg(all(libcontract, not(feature = "mock"), target_pointer_width = "64"))]
+const _: () = assert!(std::mem::offset_of!(psinfo_head, pr_contract) == 280);
+
+impl ContractId {
+    /// Returns the ID of the process contract the calling process is a
+    /// member of, read from `pr_contract` in /proc/self/psinfo; see
+    /// proc(5).
+    pub fn of_self() -> Result<ContractId, ContractError> {
+        psinfo_contract(Path::new("/proc/self/psinfo"))
+    }
+
+    /// Returns the ID of the process contract that process `pid` is a
+    /// member of, read from /proc/`pid`/psinfo.  This fails with
+    /// [`ContractError::NotFound`] if there is no such process.
+    ///
+    /// With the "mock" feature, `pid` should be that of a
+    /// [`crate::mock::MockProcess`].
+    pub fn of_pid(pid: pid_t) -> Result<ContractId, ContractError> {
+        #[cfg(feature = "mock")]
+        if let Some(ctid) = crate::mock::contract_of(pid) {
+            return Ok(ContractId(ctid));
+        }
+
+        psinfo_contract(
+            &Path::new("/proc").join(pid.to_string()).join("psinfo"),
+        )
+    }
+}
+
+#[cfg(not(feature = "mock"))]
+fn psinfo_contract(path: &Path) -> Result<ContractId, ContractError> {
+    use std::io::Read;
+    use std::mem::size_of;
+
+    super::supported()?;
+
+    let mut buf = [0u8; size_of::<psinfo_head>()];
+    std::fs::File::open(path)?.read_exact(&mut buf)?;
+    let ps: psinfo_head =
+        unsafe { std::ptr::read_unaligned(buf.as_ptr().cast()) };
+    Ok(ContractId(ps.pr_contract as crate::ctid_t))
+}
+
+/*
+ * The calling process is never a member of a simulated contract, and
+ * neither is a simulated process that has died.
+ */
+#[cfg(feature = "mock")]
+fn psinfo_contract(_: &Path) -> Result<ContractId, ContractError> {
+    Err(ContractError::NotFound)
+}
@@ -0,0 +1,171 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::os::raw::c_uint;
+
+use libc::{id_t, pid_t};
+
+use super::{all_contracts, ContractError, Detail, DevEventSet, PrEventSet};
+use super::{DeviceStatus, PrParamSet, ProcessStatus, StatusSnapshot};
+use crate::ctfs;
+use crate::ctstate_t;
+use crate::ids::{ContractId, ZoneId};
+
+/// The information shown by `ctstat -v` for a contract; see ctstat(1).
+///
+/// Event sets, parameters and states are given by name, in lower case, as
+/// ctstat(1) shows them.  Values this crate does not know the name of are
+/// given as numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContractReport {
+    pub id: ContractId,
+    pub zoneid: ZoneId,
+    pub type_name: String,
+    pub state: String,
+    pub holder: id_t,
+    pub nevents: i32,
+    /// The time remaining, in seconds, for the holder to respond to a
+    /// negotiation, if one is in progress.
+    pub ntime: Option<i32>,
+    /// The time remaining, in seconds, before the negotiation times out.
+    pub qtime: Option<i32>,
+    pub cookie: u64,
+    pub informative: Vec<String>,
+    pub critical: Vec<String>,
+    pub process: Option<ProcessReport>,
+    pub device: Option<DeviceReport>,
+}
+
+/// The process contract specific part of a [`ContractReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessReport {
+    pub fatal: Vec<String>,
+    pub params: Vec<String>,
+    pub members: Vec<pid_t>,
+    pub inherited: Vec<ContractId>,
+    pub svc_fmri: Option<String>,
+    pub svc_ctid: Option<ContractId>,
+    pub svc_creator: Option<String>,
+    pub svc_aux: Option<String>,
+}
+
+/// The device contract specific part of a [`ContractReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceReport {
+    pub state: Option<String>,
+    pub aset: Vec<String>,
+    pub minor: Option<String>,
+    pub noneg: bool,
+}
+
+impl ContractReport {
+    /// Reports on the contract with ID `ctid`.
+    pub fn for_contract(
+        ctid: ContractId,
+    ) -> Result<ContractReport, ContractError> {
+        Ok(ContractReport::from(&StatusSnapshot::read(ctid, Detail::All)?))
+    }
+}
+
+impl From<&StatusSnapshot> for ContractReport {
+    fn from(st: &StatusSnapshot) -> ContractReport {
+        let event_names = |events| match st.type_name.as_str() {
+            ctfs::PROCESS_TYPE => {
+                let set = PrEventSet::from_bits_retain(events);
+                names(set.names(), (set - PrEventSet::all()).bits())
+            }
+            ctfs::DEVICE_TYPE => {
+                let set = DevEventSet::from_bits_retain(events);
+                names(set.names(), (set - DevEventSet::all()).bits())
+            }
+            _ => names(std::iter::empty(), events),
+        };
+
+        ContractReport {
+            id: st.id,
+            zoneid: st.zoneid,
+            type_name: st.type_name.clone(),
+            state: state_name(st.state),
+            holder: st.holder,
+            nevents: st.nevents,
+            ntime: (st.ntime >= 0).then_some(st.ntime),
+            qtime: (st.qtime >= 0).then_some(st.qtime),
+            cookie: st.cookie,
+            informative: event_names(st.informative),
+            critical: event_names(st.critical),
+            process: st.process.as_ref().map(ProcessReport::from),
+            device: st.device.as_ref().map(DeviceReport::from),
+        }
+    }
+}
+
+impl From<&ProcessStatus> for ProcessReport {
+    fn from(pr: &ProcessStatus) -> ProcessReport {
+        ProcessReport {
+            fatal: names(
+                pr.fatal.names(),
+                (pr.fatal - PrEventSet::all()).bits(),
+            ),
+            params: names(
+                pr.params.names(),
+                (pr.params - PrParamSet::all()).bits(),
+            ),
+            members: pr.members.clone().unwrap_or_default(),
+            inherited: pr.contracts.clone().unwrap_or_default(),
+            svc_fmri: pr.svc_fmri.clone(),
+            svc_ctid: pr.svc_ctid,
+            svc_creator: pr.svc_creator.clone(),
+            svc_aux: pr.svc_aux.clone(),
+        }
+    }
+}
+
+impl From<&DeviceStatus> for DeviceReport {
+    fn from(dev: &DeviceStatus) -> DeviceReport {
+        DeviceReport {
+            state: dev.state.map(|s| format!("{:?}", s).to_lowercase()),
+            aset: dev
+                .aset
+                .iter()
+                .map(|s| format!("{:?}", s).to_lowercase())
+                .collect(),
+            minor: dev.minor.clone(),
+            noneg: dev.noneg,
+        }
+    }
+}
+
+/// Reports on every contract visible to the caller, as `ctstat -av` does.
+pub fn report_all() -> Result<
+    impl Iterator<Item = Result<ContractReport, ContractError>>,
+    ContractError,
+> {
+    Ok(all_contracts()?
+        .detail(Detail::All)
+        .map(|res| res.map(|(_, st)| ContractReport::from(&st))))
+}
+
+fn state_name(state: ctstate_t) -> String {
+    match state.name() {
+        Some(name) => name.trim_start_matches("CTS_").to_lowercase(),
+        None => state.0.to_string(),
+    }
+}
+
+/*
+ * Names the flags in a set, followed by any bits that have no name.
+ */
+fn names(
+    named: impl Iterator<Item = &'static str>,
+    unknown: c_uint,
+) -> Vec<String> {
+    let mut out: Vec<String> = named.map(|n| n.to_lowercase()).collect();
+    if unknown != 0 {
+        out.push(format!("{:#x}", unknown));
+    }
+    out
+}
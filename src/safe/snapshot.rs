@@ -0,0 +1,163 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::ffi::CStr;
+use std::os::raw::c_uint;
+
+use libc::{id_t, pid_t};
+
+use super::{check, ContractError, Detail, DeviceState, DeviceStateSet};
+use super::{PrEventSet, PrParamSet, Status};
+use crate as sys;
+use crate::ctstate_t;
+use crate::ids::{ContractId, EventId, ZoneId};
+
+/// An owned copy of a contract's status.  Unlike [`Status`], which refers to
+/// a handle allocated by libcontract, a snapshot is plain data that can be
+/// kept, cloned, and sent between threads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatusSnapshot {
+    pub detail: Detail,
+    pub id: ContractId,
+    pub zoneid: ZoneId,
+    /// The name of the zone, if it could be resolved; it cannot be if the
+    /// zone has been halted since the status was read.
+    #[cfg(feature = "zones")]
+    pub zonename: Option<String>,
+    pub type_name: String,
+    pub state: ctstate_t,
+    pub holder: id_t,
+    pub nevents: i32,
+    pub ntime: i32,
+    pub qtime: i32,
+    pub nevid: EventId,
+    pub cookie: u64,
+    pub critical: c_uint,
+    pub informative: c_uint,
+    /// Status specific to process contracts, if this is one and the status
+    /// was read with at least [`Detail::Fixed`].
+    pub process: Option<ProcessStatus>,
+    /// Status specific to device contracts, if this is one and the status
+    /// was read with at least [`Detail::Fixed`].
+    pub device: Option<DeviceStatus>,
+}
+
+/// The process contract specific part of a [`StatusSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessStatus {
+    pub params: PrParamSet,
+    pub fatal: PrEventSet,
+    /// The member process IDs, present only with [`Detail::All`].
+    pub members: Option<Vec<pid_t>>,
+    /// The IDs of inherited contracts, present only with [`Detail::All`].
+    pub contracts: Option<Vec<ContractId>>,
+    pub svc_fmri: Option<String>,
+    pub svc_aux: Option<String>,
+    pub svc_ctid: Option<ContractId>,
+    pub svc_creator: Option<String>,
+}
+
+/// The device contract specific part of a [`StatusSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceStatus {
+    pub state: Option<DeviceState>,
+    pub aset: DeviceStateSet,
+    pub minor: Option<String>,
+    pub noneg: bool,
+}
+
+impl StatusSnapshot {
+    /// Reads the status of the contract with ID `ctid` and copies it.
+    pub fn read(
+        ctid: ContractId,
+        detail: Detail,
+    ) -> Result<StatusSnapshot, ContractError> {
+        StatusSnapshot::from_status(&Status::read_ctid(ctid, detail)?)
+    }
+
+    pub fn from_status(st: &Status) -> Result<StatusSnapshot, ContractError> {
+        let type_name = st.type_name().to_string_lossy().into_owned();
+        let fixed = st.detail() >= Detail::Fixed;
+
+        let process = match type_name.as_str() {
+            crate::ctfs::PROCESS_TYPE if fixed => Some(process_status(st)?),
+            _ => None,
+        };
+        let device = match type_name.as_str() {
+            crate::ctfs::DEVICE_TYPE if fixed => Some(device_status(st)?),
+            _ => None,
+        };
+
+        let zoneid = st.zoneid();
+
+        Ok(StatusSnapshot {
+            detail: st.detail(),
+            id: st.id(),
+            zoneid,
+            #[cfg(feature = "zones")]
+            zonename: zoneid.name().ok(),
+            type_name,
+            state: st.state(),
+            holder: st.holder(),
+            nevents: st.nevents(),
+            ntime: st.ntime(),
+            qtime: st.qtime(),
+            nevid: st.nevid(),
+            cookie: st.cookie(),
+            critical: st.critical(),
+            informative: st.informative(),
+            process,
+            device,
+        })
+    }
+}
+
+impl Status {
+    /// Copies this status into a [`StatusSnapshot`].
+    pub fn snapshot(&self) -> Result<StatusSnapshot, ContractError> {
+        StatusSnapshot::from_status(self)
+    }
+}
+
+fn process_status(st: &Status) -> Result<ProcessStatus, ContractError> {
+    let hdl = st.as_ptr();
+
+    let mut params = 0;
+    check(unsafe { sys::ct_pr_status_get_param(hdl, &mut params) })?;
+    let mut fatal = 0;
+    check(unsafe { sys::ct_pr_status_get_fatal(hdl, &mut fatal) })?;
+
+    let (members, contracts) = if st.detail() == Detail::All {
+        (Some(st.members()?.to_vec()), Some(st.contracts()?.to_vec()))
+    } else {
+        (None, None)
+    };
+
+    Ok(ProcessStatus {
+        params: PrParamSet::from_bits_retain(params),
+        fatal: PrEventSet::from_bits_retain(fatal),
+        members,
+        contracts,
+        svc_fmri: st.svc_fmri().ok().map(owned),
+        svc_aux: st.svc_aux().ok().map(owned),
+        svc_ctid: st.svc_ctid().ok(),
+        svc_creator: st.svc_creator().ok().map(owned),
+    })
+}
+
+fn device_status(st: &Status) -> Result<DeviceStatus, ContractError> {
+    Ok(DeviceStatus {
+        state: st.dev_state().ok(),
+        aset: st.dev_aset()?,
+        minor: st.dev_minor().ok().map(owned),
+        noneg: st.dev_noneg()?,
+    })
+}
+
+fn owned(s: &CStr) -> String {
+    s.to_string_lossy().into_owned()
+}
@@ -0,0 +1,81 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+/*
+ * Counters and gauges for contract activity, emitted through the "metrics"
+ * facade when the "metrics" feature is enabled.  Without the feature, these
+ * functions do nothing.
+ *
+ * The metrics are:
+ *
+ *   libcontract_events_received_total   counter, by event "type"
+ *   libcontract_responses_total         counter, by "response": ack, nack
+ *                                       or qack
+ *   libcontract_contracts_held          gauge: contracts created or adopted
+ *                                       through this crate, less those
+ *                                       abandoned
+ *   libcontract_queued_events           histogram: the number of events
+ *                                       queued on a contract held by the
+ *                                       caller, at each read of its status
+ *
+ * Contract IDs are never reused, so no metric is labelled by contract:
+ * every contract would leave behind a series of its own.
+ */
+
+use super::{Event, Status};
+
+#[cfg(feature = "metrics")]
+pub(super) fn event_received(ev: &Event) {
+    let ty = match ev.type_name() {
+        Some(name) => name.to_string(),
+        None => ev.event_type().to_string(),
+    };
+    metrics::counter!("libcontract_events_received_total", "type" => ty)
+        .increment(1);
+}
+
+#[cfg(feature = "metrics")]
+pub(super) fn response_sent(response: &'static str) {
+    metrics::counter!("libcontract_responses_total", "response" => response)
+        .increment(1);
+}
+
+#[cfg(feature = "metrics")]
+pub(super) fn contract_held() {
+    metrics::gauge!("libcontract_contracts_held").increment(1.0);
+}
+
+#[cfg(feature = "metrics")]
+pub(super) fn contract_released() {
+    metrics::gauge!("libcontract_contracts_held").decrement(1.0);
+}
+
+#[cfg(feature = "metrics")]
+pub(super) fn status_read(st: &Status) {
+    /*
+     * The status of contracts held by others, e.g., when enumerating every
+     * contract, says nothing about how well this process keeps up.
+     */
+    if st.state() == crate::ctstate_t::CTS_OWNED
+        && st.holder() == std::process::id() as libc::id_t
+    {
+        metrics::histogram!("libcontract_queued_events")
+            .record(st.nevents() as f64);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(super) fn event_received(_: &Event) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(super) fn response_sent(_: &'static str) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(super) fn contract_held() {}
+
+#[cfg(not(feature = "metrics"))]
+pub(super) fn contract_released() {}
+
+#[cfg(not(feature = "metrics"))]
+pub(super) fn status_read(_: &Status) {}
@@ -0,0 +1,163 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::ffi::CStr;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr;
+
+use libc::id_t;
+
+use super::{check, open_read, stats, ContractError};
+use crate as sys;
+use crate::ctfs;
+use crate::ids::{ContractId, EventId, ZoneId};
+use crate::{ct_stathdl_t, ctstate_t};
+
+/// The amount of detail to request from ct_status_read(3CONTRACT).
+///
+/// Each level includes everything provided by the levels before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Detail {
+    Common,
+    Fixed,
+    All,
+}
+
+impl Detail {
+    pub fn as_raw(self) -> c_int {
+        match self {
+            Detail::Common => sys::CTD_COMMON,
+            Detail::Fixed => sys::CTD_FIXED,
+            Detail::All => sys::CTD_ALL,
+        }
+    }
+}
+
+/// The status of a contract, as read from its status endpoint.
+#[derive(Debug)]
+pub struct Status {
+    hdl: *mut ct_stathdl_t,
+    detail: Detail,
+}
+
+impl Status {
+    /// Reads the status from an already open status endpoint.
+    pub fn read(
+        fd: BorrowedFd<'_>,
+        detail: Detail,
+    ) -> Result<Status, ContractError> {
+        let mut hdl = ptr::null_mut();
+        check(unsafe {
+            sys::ct_status_read(fd.as_raw_fd(), detail.as_raw(), &mut hdl)
+        })?;
+        let st = Status { hdl, detail };
+        stats::status_read(&st);
+        Ok(st)
+    }
+
+    /// Opens the status endpoint of the contract with ID `ctid`, reads its
+    /// status, and closes the endpoint again.
+    pub fn read_ctid(
+        ctid: ContractId,
+        detail: Detail,
+    ) -> Result<Status, ContractError> {
+        let fd = open_read(&ctfs::status_path(ctid.as_raw()))?;
+        Status::read(fd.as_fd(), detail)
+    }
+
+    /// Returns the level of detail with which the status was read.
+    pub fn detail(&self) -> Detail {
+        self.detail
+    }
+
+    pub fn id(&self) -> ContractId {
+        ContractId(unsafe { sys::ct_status_get_id(self.hdl) })
+    }
+
+    pub fn zoneid(&self) -> ZoneId {
+        ZoneId(unsafe { sys::ct_status_get_zoneid(self.hdl) })
+    }
+
+    /// Returns the name of the contract type, e.g., "process".
+    pub fn type_name(&self) -> &CStr {
+        unsafe { CStr::from_ptr(sys::ct_status_get_type(self.hdl)) }
+    }
+
+    pub fn state(&self) -> ctstate_t {
+        unsafe { sys::ct_status_get_state(self.hdl) }
+    }
+
+    /// Returns the ID of the process holding the contract, if the contract
+    /// is owned, or of the process contract that has inherited it.
+    pub fn holder(&self) -> id_t {
+        unsafe { sys::ct_status_get_holder(self.hdl) }
+    }
+
+    pub fn nevents(&self) -> c_int {
+        unsafe { sys::ct_status_get_nevents(self.hdl) }
+    }
+
+    pub fn ntime(&self) -> c_int {
+        unsafe { sys::ct_status_get_ntime(self.hdl) }
+    }
+
+    pub fn qtime(&self) -> c_int {
+        unsafe { sys::ct_status_get_qtime(self.hdl) }
+    }
+
+    pub fn nevid(&self) -> EventId {
+        EventId(unsafe { sys::ct_status_get_nevid(self.hdl) })
+    }
+
+    pub fn cookie(&self) -> u64 {
+        unsafe { sys::ct_status_get_cookie(self.hdl) }
+    }
+
+    pub fn informative(&self) -> c_uint {
+        unsafe { sys::ct_status_get_informative(self.hdl) }
+    }
+
+    pub fn critical(&self) -> c_uint {
+        unsafe { sys::ct_status_get_critical(self.hdl) }
+    }
+
+    pub fn as_ptr(&self) -> *mut ct_stathdl_t {
+        self.hdl
+    }
+
+    /// Fails with [`ContractError::InvalidArgument`] unless the status was
+    /// read with at least the given level of detail.
+    pub(super) fn require(&self, detail: Detail) -> Result<(), ContractError> {
+        if self.detail() < detail {
+            return Err(ContractError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Status {
+    fn drop(&mut self) {
+        unsafe { sys::ct_status_free(self.hdl) };
+    }
+}
+
+/*
+ * Strings returned by the status accessors point into the status handle, and
+ * so live as long as it does.
+ */
+pub(super) fn status_str<'a, F>(get: F) -> Result<&'a CStr, ContractError>
+where
+    F: FnOnce(*mut *mut c_char) -> c_int,
+{
+    let mut p = ptr::null_mut();
+    check(get(&mut p))?;
+    if p.is_null() {
+        return Err(ContractError::NotFound);
+    }
+
+    Ok(unsafe { CStr::from_ptr(p) })
+}
@@ -0,0 +1,440 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::io;
+use std::os::raw::c_uint;
+use std::process::{Child, Command, ExitStatus};
+use std::thread;
+use std::time::Duration;
+
+use super::{Contract, ContractCommandExt, ContractError, PrEventSet};
+use super::{ProcessTemplateBuilder, Template};
+use crate as sys;
+
+/// Decides when a [`Supervisor`] restarts the processes it supervises.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// The events that cause the contract to be restarted once it empties.
+    /// With [`PrEventSet::EXIT`] in the set, the processes are restarted
+    /// however they exit; with only [`PrEventSet::CORE`], say, they are
+    /// restarted only if one of them dumped core.
+    pub restart_on: PrEventSet,
+    /// The number of restarts after which the supervisor gives up, or `None`
+    /// to restart indefinitely.
+    pub max_restarts: Option<u32>,
+    /// The delay before the first restart, which doubles with each
+    /// subsequent restart up to `max_backoff`.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    /// Restarts after a core dump or a hardware error, up to 5 times.
+    fn default() -> RestartPolicy {
+        RestartPolicy {
+            restart_on: PrEventSet::CORE | PrEventSet::HWERR,
+            max_restarts: Some(5),
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// How [`Supervisor::run`] finished.
+#[derive(Debug)]
+pub enum SupervisorExit {
+    /// The contract emptied without any event that calls for a restart.
+    /// `status` is the exit status of the process that was spawned.
+    Stopped { status: ExitStatus, restarts: u32 },
+    /// The contract needed restarting, but the policy allows no more
+    /// restarts.  `event` is the CT_PR_EV_* event that called for the
+    /// restart.
+    GaveUp { status: ExitStatus, restarts: u32, event: c_uint },
+}
+
+/// Runs a command in a process contract, and restarts it according to a
+/// [`RestartPolicy`], in the manner of an SMF restarter.
+///
+/// Each run of the command gets a new contract, created from the template
+/// configuration with CT_PR_EV_EMPTY added to the critical events and the
+/// policy's restart events added to the informative events.  The contract
+/// is abandoned once it empties.
+#[derive(Debug)]
+pub struct Supervisor {
+    command: Command,
+    template: ProcessTemplateBuilder,
+    policy: RestartPolicy,
+}
+
+impl Supervisor {
+    pub fn new(
+        command: Command,
+        template: ProcessTemplateBuilder,
+        policy: RestartPolicy,
+    ) -> Supervisor {
+        Supervisor { command, template, policy }
+    }
+
+    pub fn policy(&self) -> &RestartPolicy {
+        &self.policy
+    }
+
+    /// Spawns the command and supervises it, blocking until it stops or the
+    /// policy gives up on it.  An error is returned if a contract cannot be
+    /// created or watched.
+    pub fn run(&mut self) -> Result<SupervisorExit, ContractError> {
+        self.run_with(|command, tmpl| command.spawn_in_contract(tmpl))
+    }
+
+    fn run_with<C: Supervised>(
+        &mut self,
+        mut spawn: impl FnMut(
+            &mut Command,
+            &Template,
+        ) -> Result<(C, Contract), ContractError>,
+    ) -> Result<SupervisorExit, ContractError> {
+        let mut restarts = 0;
+        let mut backoff = self.policy.backoff;
+
+        loop {
+            let (status, event) = self.run_once(&mut spawn)?;
+
+            let Some(event) = event else {
+                return Ok(SupervisorExit::Stopped { status, restarts });
+            };
+            if self.policy.max_restarts.is_some_and(|max| restarts >= max) {
+                return Ok(SupervisorExit::GaveUp { status, restarts, event });
+            }
+
+            thread::sleep(backoff);
+            backoff = next_backoff(backoff, self.policy.max_backoff);
+            restarts += 1;
+        }
+    }
+
+    /*
+     * Runs the command once, until its contract empties.  Returns the exit
+     * status of the child, and the event that calls for a restart, if any.
+     */
+    fn run_once<C: Supervised>(
+        &mut self,
+        spawn: &mut impl FnMut(
+            &mut Command,
+            &Template,
+        ) -> Result<(C, Contract), ContractError>,
+    ) -> Result<(ExitStatus, Option<c_uint>), ContractError> {
+        let tmpl = self.template.build()?;
+        tmpl.set_critical(tmpl.critical()? | sys::CT_PR_EV_EMPTY)?;
+        tmpl.set_informative(
+            tmpl.informative()? | self.policy.restart_on.bits(),
+        )?;
+
+        let (mut child, ct) = spawn(&mut self.command, &tmpl)?;
+        let ct = ct.abandon_on_drop();
+
+        let event = match watch(&ct, self.policy.restart_on) {
+            Ok(event) => event,
+            Err(e) => {
+                /*
+                 * The contract has not been seen to empty, so the child may
+                 * well still be running; it is killed and reaped rather than
+                 * waited for, and the guard then abandons the contract.
+                 */
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+        };
+        let status = child.wait()?;
+
+        Ok((status, event))
+    }
+}
+
+/*
+ * The processes a supervisor runs, so that they can be simulated in tests.
+ */
+trait Supervised {
+    fn kill(&mut self) -> io::Result<()>;
+    fn wait(&mut self) -> io::Result<ExitStatus>;
+}
+
+impl Supervised for Child {
+    fn kill(&mut self) -> io::Result<()> {
+        Child::kill(self)
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        Child::wait(self)
+    }
+}
+
+/*
+ * Doubles the delay before the next restart, up to `max`.  A `max` of
+ * Duration::MAX means there is no cap, so this must not overflow.
+ */
+fn next_backoff(backoff: Duration, max: Duration) -> Duration {
+    backoff.saturating_mul(2).min(max)
+}
+
+fn watch(
+    ct: &Contract,
+    restart_on: PrEventSet,
+) -> Result<Option<c_uint>, ContractError> {
+    let events = ct.events()?;
+    let mut restart = None;
+
+    loop {
+        let ev = events.read()?;
+        let ty = ev.event_type();
+
+        /*
+         * Critical events are acknowledged so that they do not linger in the
+         * queue; events that need no response are fine as they are.
+         */
+        match ev.ack(ct) {
+            Ok(()) | Err(ContractError::ResponseNotAllowed) => (),
+            Err(e) => return Err(e),
+        }
+
+        if ty == sys::CT_PR_EV_EMPTY {
+            return Ok(restart);
+        }
+        if restart.is_none()
+            && restart_on.intersects(PrEventSet::from_bits_retain(ty))
+        {
+            restart = Some(ty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let max = Duration::from_secs(60);
+        assert_eq!(
+            next_backoff(Duration::from_secs(1), max),
+            Duration::from_secs(2)
+        );
+        assert_eq!(next_backoff(Duration::from_secs(40), max), max);
+        assert_eq!(next_backoff(Duration::ZERO, max), Duration::ZERO);
+        assert_eq!(
+            next_backoff(
+                Duration::MAX / 2 + Duration::from_secs(1),
+                Duration::MAX
+            ),
+            Duration::MAX
+        );
+        assert_eq!(next_backoff(Duration::MAX, Duration::MAX), Duration::MAX);
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod mock_tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::os::unix::process::ExitStatusExt;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::ctfs;
+    use crate::ids::ContractId;
+    use crate::mock::{self, MockProcess};
+    use crate::safe::{all_contracts, Detail, Status};
+
+    /*
+     * How a simulated child ends, as soon as it has been spawned.
+     */
+    #[derive(Debug, Clone, Copy)]
+    enum Fate {
+        Exit(i32),
+        Core(i32),
+        Signal(i32),
+        /*
+         * The child keeps running, and its contract is returned under an ID
+         * that does not exist, so that watching it fails.
+         */
+        Unwatchable,
+    }
+
+    #[derive(Debug)]
+    struct MockChild {
+        process: Option<MockProcess>,
+        status: i32,
+        log: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl Supervised for MockChild {
+        fn kill(&mut self) -> io::Result<()> {
+            self.log.borrow_mut().push("kill");
+            if let Some(p) = self.process.take() {
+                p.kill(libc::SIGKILL, None)?;
+                self.status = libc::SIGKILL;
+            }
+            Ok(())
+        }
+
+        fn wait(&mut self) -> io::Result<ExitStatus> {
+            self.log.borrow_mut().push("wait");
+            assert!(self.process.is_none(), "waiting for a running child");
+            Ok(ExitStatus::from_raw(self.status))
+        }
+    }
+
+    fn supervisor(restart_on: PrEventSet, max: Option<u32>) -> Supervisor {
+        /*
+         * The events that end the children are made critical, so that they
+         * are still pending when the supervisor starts to watch.
+         */
+        let template = ProcessTemplateBuilder::new()
+            .critical(PrEventSet::EXIT | PrEventSet::CORE | PrEventSet::SIGNAL);
+        let policy = RestartPolicy {
+            restart_on,
+            max_restarts: max,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::MAX,
+        };
+        Supervisor::new(Command::new("/nonexistent"), template, policy)
+    }
+
+    /*
+     * Runs the supervisor over simulated children with the given fates,
+     * returning the outcome, the contract of each child, and a log of the
+     * calls made on the children.
+     */
+    fn run(
+        sup: &mut Supervisor,
+        fates: &[Fate],
+    ) -> (
+        Result<SupervisorExit, ContractError>,
+        Vec<ContractId>,
+        Vec<&'static str>,
+    ) {
+        let mut fates: VecDeque<_> = fates.iter().copied().collect();
+        let mut ctids = Vec::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let res = sup.run_with(|_, tmpl| {
+            let fate = fates.pop_front().expect("too many restarts");
+            let p = {
+                let _active = tmpl.activate_guard()?;
+                mock::spawn()?
+            };
+            let ctid = p.ctid();
+            ctids.push(ctid);
+
+            let mut child =
+                MockChild { process: None, status: 0, log: Rc::clone(&log) };
+            let mut ctid_seen = ctid;
+            match fate {
+                Fate::Exit(code) => {
+                    p.exit(code)?;
+                    child.status = code << 8;
+                }
+                Fate::Core(sig) => {
+                    p.core(sig)?;
+                    child.status = sig | 0x80;
+                }
+                Fate::Signal(sig) => {
+                    p.kill(sig, None)?;
+                    child.status = sig;
+                }
+                Fate::Unwatchable => {
+                    child.process = Some(p);
+                    ctid_seen = ContractId(i32::MAX);
+                }
+            }
+
+            let ctl = mock::open(&ctfs::ctl_path(ctid.as_raw()))?;
+            Ok((child, Contract::from_ctl(ctid_seen, ctl)))
+        });
+
+        let log = log.borrow().clone();
+        (res, ctids, log)
+    }
+
+    fn listed(ctid: ContractId) -> bool {
+        all_contracts().unwrap().any(|res| res.unwrap().0 == ctid)
+    }
+
+    #[test]
+    fn restarts_after_core() {
+        let mut sup = supervisor(PrEventSet::CORE, Some(5));
+        let fates = [Fate::Core(libc::SIGSEGV), Fate::Exit(3)];
+        let (res, ctids, log) = run(&mut sup, &fates);
+
+        let Ok(SupervisorExit::Stopped { status, restarts }) = res else {
+            panic!("unexpected outcome: {res:?}");
+        };
+        assert_eq!(restarts, 1);
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(log, ["wait", "wait"]);
+
+        /*
+         * Each contract was abandoned once it had emptied.
+         */
+        assert_eq!(ctids.len(), 2);
+        assert!(ctids.iter().all(|&ctid| !listed(ctid)));
+    }
+
+    #[test]
+    fn stops_on_events_outside_the_policy() {
+        let mut sup = supervisor(PrEventSet::CORE | PrEventSet::HWERR, None);
+        let (res, _, _) = run(&mut sup, &[Fate::Signal(libc::SIGTERM)]);
+
+        let Ok(SupervisorExit::Stopped { status, restarts: 0 }) = res else {
+            panic!("unexpected outcome: {res:?}");
+        };
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+    }
+
+    #[test]
+    fn gives_up_after_max_restarts() {
+        let mut sup = supervisor(PrEventSet::EXIT | PrEventSet::CORE, Some(2));
+        let fates = [Fate::Exit(0), Fate::Core(libc::SIGABRT), Fate::Exit(1)];
+        let (res, ctids, _) = run(&mut sup, &fates);
+
+        /*
+         * With EXIT in the policy, every exit calls for a restart, so the
+         * supervisor only stops once it runs out of restarts.
+         */
+        let Ok(SupervisorExit::GaveUp { status, restarts, event }) = res else {
+            panic!("unexpected outcome: {res:?}");
+        };
+        assert_eq!(restarts, 2);
+        assert_eq!(event, sys::CT_PR_EV_EXIT);
+        assert_eq!(status.code(), Some(1));
+        assert_eq!(ctids.len(), 3);
+    }
+
+    #[test]
+    fn first_restart_event_is_reported() {
+        let mut sup = supervisor(PrEventSet::EXIT | PrEventSet::CORE, Some(0));
+        let (res, _, _) = run(&mut sup, &[Fate::Core(libc::SIGSEGV)]);
+
+        let Ok(SupervisorExit::GaveUp { restarts: 0, event, .. }) = res else {
+            panic!("unexpected outcome: {res:?}");
+        };
+        assert_eq!(event, sys::CT_PR_EV_CORE);
+    }
+
+    #[test]
+    fn failed_watch_kills_and_reaps_child() {
+        let mut sup = supervisor(PrEventSet::CORE, None);
+        let (res, ctids, log) = run(&mut sup, &[Fate::Unwatchable]);
+
+        assert!(matches!(res, Err(ContractError::NotFound)));
+        assert_eq!(log, ["kill", "wait"]);
+
+        /*
+         * The child was killed, emptying its contract, which was then
+         * abandoned.
+         */
+        assert!(Status::read_ctid(ctids[0], Detail::Common).is_err());
+        assert!(!listed(ctids[0]));
+    }
+}
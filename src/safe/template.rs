@@ -0,0 +1,158 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::raw::c_uint;
+use std::path::Path;
+
+use super::{check, impl_as_fd, open_rdwr, stats, ContractError};
+use crate as sys;
+use crate::ids::ContractId;
+
+/// An open contract template; see contract(5) and ct_tmpl_activate(3CONTRACT).
+#[derive(Debug)]
+pub struct Template {
+    fd: OwnedFd,
+}
+
+impl Template {
+    /// Opens the template endpoint at `path`, e.g.,
+    /// `/system/contract/process/template`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Template, ContractError> {
+        Ok(Template { fd: open_rdwr(path.as_ref())? })
+    }
+
+    /// Makes this the active template for the calling thread.
+    pub fn activate(&self) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_tmpl_activate(self.fd.as_raw_fd()) })
+    }
+
+    /// Makes this the active template for the calling thread until the
+    /// returned guard is dropped, at which point the active template is
+    /// cleared again (including during a panic).  This ensures the template
+    /// cannot be left active by accident, and so apply to unrelated
+    /// processes created by the thread later.
+    pub fn activate_guard(&self) -> Result<ActiveTemplate<'_>, ContractError> {
+        self.activate()?;
+        Ok(ActiveTemplate { tmpl: self, _thread: PhantomData })
+    }
+
+    /// Clears the active template for the calling thread.  The active
+    /// template need not be this one.
+    pub fn clear(&self) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_tmpl_clear(self.fd.as_raw_fd()) })
+    }
+
+    /// Creates a new contract from this template, returning its ID.  This
+    /// fails with [`ContractError::NotEmpty`] if the template is a process
+    /// contract template whose transfer term names a contract that has
+    /// members.
+    pub fn create(&self) -> Result<ContractId, ContractError> {
+        let mut ctid = 0;
+        check(unsafe { sys::ct_tmpl_create(self.fd.as_raw_fd(), &mut ctid) })
+            .map_err(|e| match e.errno() {
+            /*
+             * The subject of a process contract template's transfer
+             * term may have gained members since the term was set.
+             */
+            Some(libc::ENOTEMPTY)
+                if self.transfer().is_ok_and(|ct| ct.as_raw() != 0) =>
+            {
+                ContractError::NotEmpty
+            }
+            _ => e,
+        })?;
+        stats::contract_held();
+        Ok(ContractId(ctid))
+    }
+
+    pub fn set_cookie(&self, cookie: u64) -> Result<(), ContractError> {
+        check(unsafe { sys::ct_tmpl_set_cookie(self.fd.as_raw_fd(), cookie) })
+    }
+
+    /// Sets the critical event set.  The events are those of the template's
+    /// contract type, e.g., a [`super::PrEventSet`].
+    pub fn set_critical<E: Into<c_uint>>(
+        &self,
+        events: E,
+    ) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_tmpl_set_critical(self.fd.as_raw_fd(), events.into())
+        })
+    }
+
+    /// Sets the informative event set.  The events are those of the
+    /// template's contract type, e.g., a [`super::PrEventSet`].
+    pub fn set_informative<E: Into<c_uint>>(
+        &self,
+        events: E,
+    ) -> Result<(), ContractError> {
+        check(unsafe {
+            sys::ct_tmpl_set_informative(self.fd.as_raw_fd(), events.into())
+        })
+    }
+
+    pub fn cookie(&self) -> Result<u64, ContractError> {
+        let mut cookie = 0;
+        check(unsafe {
+            sys::ct_tmpl_get_cookie(self.fd.as_raw_fd(), &mut cookie)
+        })?;
+        Ok(cookie)
+    }
+
+    pub fn critical(&self) -> Result<c_uint, ContractError> {
+        let mut events = 0;
+        check(unsafe {
+            sys::ct_tmpl_get_critical(self.fd.as_raw_fd(), &mut events)
+        })?;
+        Ok(events)
+    }
+
+    pub fn informative(&self) -> Result<c_uint, ContractError> {
+        let mut events = 0;
+        check(unsafe {
+            sys::ct_tmpl_get_informative(self.fd.as_raw_fd(), &mut events)
+        })?;
+        Ok(events)
+    }
+}
+
+impl From<OwnedFd> for Template {
+    /// Wraps an already open template endpoint.
+    fn from(fd: OwnedFd) -> Template {
+        Template { fd }
+    }
+}
+
+impl_as_fd!(Template, fd);
+
+/// A template that is active on the current thread; see
+/// [`Template::activate_guard`].
+#[must_use = "the template is cleared as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct ActiveTemplate<'a> {
+    tmpl: &'a Template,
+    /*
+     * The active template is a property of the thread, so the guard must
+     * not be dropped on a different one.
+     */
+    _thread: PhantomData<*const ()>,
+}
+
+impl ActiveTemplate<'_> {
+    /// Clears the active template, returning any error from doing so rather
+    /// than ignoring it as dropping the guard does.
+    pub fn clear(self) -> Result<(), ContractError> {
+        let guard = ManuallyDrop::new(self);
+        guard.tmpl.clear()
+    }
+}
+
+impl Drop for ActiveTemplate<'_> {
+    fn drop(&mut self) {
+        let _ = self.tmpl.clear();
+    }
+}
@@ -0,0 +1,97 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use super::ContractError;
+use crate::ids::ZoneId;
+
+/*
+ * The maximum length of a zone name, including the terminating NUL; see
+ * sys/zone.h.
+ */
+const ZONENAME_MAX: usize = 64;
+
+impl ZoneId {
+    /// Returns the name of the zone, as getzonenamebyid(3C) does.  This
+    /// fails with [`ContractError::InvalidArgument`] if there is no such
+    /// zone, e.g., because it has been halted since the ID was read.
+    pub fn name(self) -> Result<String, ContractError> {
+        zonename::by_id(self)
+    }
+
+    /// Returns the ID of the zone named `name`, as getzoneidbyname(3C) does,
+    /// e.g., to pass to [`super::AllContracts::in_zone`] or
+    /// [`super::EventEndpoint::in_zone`].  This fails with
+    /// [`ContractError::InvalidArgument`] if there is no such zone.
+    pub fn by_name(name: &str) -> Result<ZoneId, ContractError> {
+        zonename::by_name(&super::cstring(name)?)
+    }
+}
+
+#[cfg(libcontract)]
+mod zonename {
+    use std::ffi::CStr;
+    use std::io;
+    use std::os::raw::c_char;
+
+    use super::ZONENAME_MAX;
+    use crate::ids::ZoneId;
+    use crate::safe::ContractError;
+
+    extern "C" {
+        /*
+         * See getzonenamebyid(3C); this is not provided by the libc crate.
+         */
+        fn getzonenamebyid(
+            id: crate::zoneid_t,
+            buf: *mut c_char,
+            buflen: libc::size_t,
+        ) -> libc::ssize_t;
+
+        /*
+         * See getzoneidbyname(3C).
+         */
+        fn getzoneidbyname(name: *const c_char) -> crate::zoneid_t;
+    }
+
+    pub(super) fn by_id(zoneid: ZoneId) -> Result<String, ContractError> {
+        let mut buf = [0 as c_char; ZONENAME_MAX];
+        let rv = unsafe {
+            getzonenamebyid(zoneid.as_raw(), buf.as_mut_ptr(), buf.len())
+        };
+        if rv < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        Ok(name.to_string_lossy().into_owned())
+    }
+
+    pub(super) fn by_name(name: &CStr) -> Result<ZoneId, ContractError> {
+        let zoneid = unsafe { getzoneidbyname(name.as_ptr()) };
+        if zoneid < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(ZoneId(zoneid))
+    }
+}
+
+/*
+ * Elsewhere, there are no zones to name.
+ */
+#[cfg(not(libcontract))]
+mod zonename {
+    use std::ffi::CStr;
+
+    use crate::ids::ZoneId;
+    use crate::safe::ContractError;
+
+    pub(super) fn by_id(_: ZoneId) -> Result<String, ContractError> {
+        Err(ContractError::NotSupported)
+    }
+
+    pub(super) fn by_name(_: &CStr) -> Result<ZoneId, ContractError> {
+        Err(ContractError::NotSupported)
+    }
+}
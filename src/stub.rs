@@ -0,0 +1,63 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+/*
+ * On systems without contracts, the libcontract(3LIB) functions are defined
+ * to do nothing, so that crates depending on this one can still be built,
+ * tested and documented there.  The value a function returns depends only on
+ * its return type:
+ *
+ *   - Every function returning a c_int returns ENOTSUP.  For the functions
+ *     that can fail, the safe wrappers then report
+ *     ContractError::NotSupported.  The getters that return an ID or a count
+ *     as a c_int, such as ct_status_get_nevents() or ct_event_get_ctid(),
+ *     return ENOTSUP too, as the type cannot tell them apart; they take a
+ *     handle that can never be obtained here, so the value is never seen.
+ *
+ *   - Every other function returns a zero value, a null pointer, or
+ *     CTS_DEAD; these also only take handles that can never be obtained.
+ */
+
+use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr;
+
+use crate::ctstate_t;
+
+pub(crate) trait Stub {
+    fn stub() -> Self;
+}
+
+impl Stub for c_int {
+    fn stub() -> c_int {
+        libc::ENOTSUP
+    }
+}
+
+impl Stub for c_uint {
+    fn stub() -> c_uint {
+        0
+    }
+}
+
+impl Stub for u64 {
+    fn stub() -> u64 {
+        0
+    }
+}
+
+impl Stub for ctstate_t {
+    fn stub() -> ctstate_t {
+        ctstate_t::CTS_DEAD
+    }
+}
+
+impl Stub for *const c_char {
+    fn stub() -> *const c_char {
+        ptr::null()
+    }
+}
+
+impl Stub for () {
+    fn stub() {}
+}
@@ -0,0 +1,35 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+use std::env;
+use std::path::PathBuf;
+
+/*
+ * Generates bindings for the contract structures, enumerations and constants
+ * from the system headers, so that the tests can compare them with those in
+ * libcontract-sys.  The bindings are only used by the tests, and never to
+ * call into the library.
+ */
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=wrapper.h");
+
+    let bindings = bindgen::Builder::default()
+        .header("wrapper.h")
+        .allowlist_type("ct_event_t|ct_status_t|ct_param_t")
+        .allowlist_type("ctstate_t|ct_typeid_t")
+        .allowlist_item("CT[A-Z]*_[A-Z_]+")
+        .prepend_enum_name(false)
+        /*
+         * The ioctl requests are defined with function-like macros, which
+         * bindgen can only evaluate with the help of clang.
+         */
+        .clang_macro_fallback()
+        .layout_tests(false)
+        .generate()
+        .expect("generating bindings for the contract headers");
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    bindings.write_to_file(out.join("sys.rs")).expect("writing bindings");
+}
@@ -0,0 +1,215 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+/*
+ * Checks that the structures, enumerations and constants in libcontract-sys
+ * match those in the system headers, as seen by bindgen.  The private
+ * structures are not Committed interfaces, so these are most likely to drift
+ * between releases.
+ */
+
+use std::mem::{align_of, offset_of, size_of};
+
+use libcontract_sys as sys;
+
+#[allow(dead_code, non_camel_case_types, non_upper_case_globals)]
+mod c {
+    include!(concat!(env!("OUT_DIR"), "/sys.rs"));
+}
+
+/*
+ * Returns the size of the field that "get" borrows from a T.
+ */
+fn field_size<T, F>(_: fn(&T) -> &F) -> usize {
+    size_of::<F>()
+}
+
+macro_rules! check_struct {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        assert_eq!(
+            size_of::<sys::$name>(),
+            size_of::<c::$name>(),
+            "size of {}",
+            stringify!($name),
+        );
+        assert_eq!(
+            align_of::<sys::$name>(),
+            align_of::<c::$name>(),
+            "alignment of {}",
+            stringify!($name),
+        );
+        $(
+            assert_eq!(
+                offset_of!(sys::$name, $field),
+                offset_of!(c::$name, $field),
+                "offset of {}.{}",
+                stringify!($name),
+                stringify!($field),
+            );
+            assert_eq!(
+                field_size(|s: &sys::$name| &s.$field),
+                field_size(|s: &c::$name| &s.$field),
+                "size of {}.{}",
+                stringify!($name),
+                stringify!($field),
+            );
+        )*
+    };
+}
+
+macro_rules! check_enum {
+    ($name:ident { $($value:ident),* $(,)? }) => {
+        assert_eq!(
+            size_of::<sys::$name>(),
+            size_of::<c::$name>(),
+            "size of {}",
+            stringify!($name),
+        );
+        assert_eq!(
+            align_of::<sys::$name>(),
+            align_of::<c::$name>(),
+            "alignment of {}",
+            stringify!($name),
+        );
+        $(
+            assert_eq!(
+                i64::from(sys::$name::$value.0),
+                c::$value as i64,
+                "value of {}",
+                stringify!($value),
+            );
+        )*
+    };
+}
+
+macro_rules! check_consts {
+    ($($name:ident),* $(,)?) => {
+        check_consts!(in sys: $($name),*);
+    };
+    (in $module:path: $($name:ident),* $(,)?) => {
+        $(
+            assert_eq!(
+                { use $module as m; m::$name as i64 },
+                c::$name as i64,
+                "value of {}",
+                stringify!($name),
+            );
+        )*
+    };
+}
+
+#[test]
+fn ct_event_t() {
+    check_struct!(ct_event_t {
+        ctev_id,
+        ctev_pad1,
+        ctev_evid,
+        ctev_cttype,
+        ctev_flags,
+        ctev_type,
+        ctev_nbytes,
+        ctev_goffset,
+        ctev_pad2,
+        ctev_buffer,
+    });
+}
+
+#[test]
+fn ct_status_t() {
+    check_struct!(ct_status_t {
+        ctst_id,
+        ctst_zoneid,
+        ctst_type,
+        ctst_holder,
+        ctst_state,
+        ctst_nevents,
+        ctst_ntime,
+        ctst_qtime,
+        ctst_nevid,
+        ctst_detail,
+        ctst_nbytes,
+        ctst_critical,
+        ctst_informative,
+        ctst_cookie,
+        ctst_buffer,
+    });
+}
+
+#[test]
+fn ct_param_t() {
+    check_struct!(ct_param_t { ctpm_id, ctpm_size, ctpm_value });
+}
+
+#[test]
+fn enums() {
+    check_enum!(ctstate_t { CTS_OWNED, CTS_INHERITED, CTS_ORPHAN, CTS_DEAD });
+    check_enum!(ct_typeid_t { CTT_PROCESS, CTT_DEVICE });
+}
+
+#[test]
+fn consts() {
+    check_consts!(
+        CTD_COMMON,
+        CTD_FIXED,
+        CTD_ALL,
+        CT_EV_NEGEND,
+        CT_PARAM_MAX_SIZE,
+        CTE_ACK,
+        CTE_INFO,
+        CTE_NEG,
+        CTP_COOKIE,
+        CTP_EV_INFO,
+        CTP_EV_CRITICAL,
+        CTPP_SUBSUME,
+        CTPP_PARAMS,
+        CTPP_EV_FATAL,
+        CTPP_SVC_FMRI,
+        CTPP_CREATOR_AUX,
+    );
+
+    check_consts!(
+        CT_PR_EV_EMPTY,
+        CT_PR_EV_FORK,
+        CT_PR_EV_EXIT,
+        CT_PR_EV_CORE,
+        CT_PR_EV_SIGNAL,
+        CT_PR_EV_HWERR,
+        CT_PR_ALLEVENT,
+        CT_PR_ALLFATAL,
+        CT_PR_INHERIT,
+        CT_PR_NOORPHAN,
+        CT_PR_PGRPONLY,
+        CT_PR_REGENT,
+        CT_PR_KEEP_EXEC,
+        CT_PR_ALLPARAM,
+    );
+
+    check_consts!(
+        CT_DEV_EV_ONLINE,
+        CT_DEV_EV_DEGRADED,
+        CT_DEV_EV_OFFLINE,
+        CT_DEV_ALLEVENT,
+    );
+
+    check_consts!(in sys::ctfs_ioctl:
+        CT_TACTIVATE,
+        CT_TCLEAR,
+        CT_TCREATE,
+        CT_TSET,
+        CT_TGET,
+        CT_CABANDON,
+        CT_CACK,
+        CT_CQREQ,
+        CT_CADOPT,
+        CT_CNEWCT,
+        CT_CNACK,
+        CT_SSTATUS,
+        CT_SCOOKIE,
+        CT_ERESET,
+        CT_ERECV,
+        CT_ECRECV,
+        CT_ENEXT,
+        CT_ERELIABLE,
+    );
+}
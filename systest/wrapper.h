@@ -0,0 +1,10 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+#include <sys/types.h>
+#include <sys/contract.h>
+#include <sys/ctfs.h>
+#include <sys/contract/process.h>
+#include <sys/contract/device.h>
+#include <libcontract.h>
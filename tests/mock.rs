@@ -0,0 +1,621 @@
+/*
+ * Copyright 2024 Oxide Computer Company
+ */
+
+/*
+ * Tests of the "safe" interfaces against the simulated contract subsystem.
+ * The simulation is shared by every test in this file, and the tests run in
+ * parallel, so each test only looks at the contracts it created itself.
+ */
+
+use libcontract_sys::ct_typeid_t;
+use libcontract_sys::ctstate_t;
+use libcontract_sys::ids::{ContractId, EventId, ZoneId, GLOBAL_ZONEID};
+use libcontract_sys::mock::{self, MockProcess};
+use libcontract_sys::safe::{
+    all_contracts, orphans, report_all, AdoptionManager, AllContracts,
+    Contract, ContractError, ContractReport, CteFlags, Detail, DeviceEvent,
+    DeviceEventKind, DeviceState, DeviceStateSet, DeviceTemplateBuilder,
+    EndpointKind, Event, EventEndpoint, Negotiation, NegotiationState,
+    PrEventSet, PrParamSet, ProcessTemplateBuilder, Recovery, Status,
+    StatusSnapshot,
+};
+use libcontract_sys::{CT_PR_EV_EMPTY, CT_PR_EV_EXIT, CT_PR_EV_FORK};
+
+/*
+ * Creates a simulated process in a new contract with the given terms.
+ */
+fn spawn(config: &ProcessTemplateBuilder) -> MockProcess {
+    let tmpl = config.build().unwrap();
+    let _active = tmpl.activate_guard().unwrap();
+    mock::spawn().unwrap()
+}
+
+/*
+ * Reads every event that is currently queued on an endpoint.
+ */
+fn drain(events: &EventEndpoint) -> Vec<Event> {
+    events.set_nonblocking(true).unwrap();
+    let mut out = Vec::new();
+    loop {
+        match events.read() {
+            Ok(ev) => out.push(ev),
+            Err(ContractError::WouldBlock) => return out,
+            Err(e) => panic!("reading events: {e}"),
+        }
+    }
+}
+
+fn types(events: &[Event]) -> Vec<u32> {
+    events.iter().map(Event::event_type).collect()
+}
+
+#[test]
+fn create_status_events() {
+    let config = ProcessTemplateBuilder::new()
+        .critical(PrEventSet::EMPTY)
+        .informative(PrEventSet::FORK | PrEventSet::EXIT)
+        .cookie(0xc0ffee);
+
+    let ctid = config.create().unwrap();
+    let st = Status::read_ctid(ctid, Detail::All).unwrap();
+    assert_eq!(st.id(), ctid);
+    assert_eq!(st.state(), ctstate_t::CTS_OWNED);
+    assert_eq!(st.holder(), std::process::id() as libc::id_t);
+    assert_eq!(st.cookie(), 0xc0ffee);
+    assert_eq!(st.critical(), CT_PR_EV_EMPTY);
+    assert!(st.members().unwrap().is_empty());
+
+    let parent = spawn(&config);
+    let ct = Contract::open(parent.ctid()).unwrap();
+    let events = ct.events().unwrap();
+    let child = parent.fork().unwrap();
+    let st = ct.status(Detail::All).unwrap();
+    assert_eq!(st.members().unwrap(), [parent.pid(), child.pid()]);
+    assert_eq!(ContractId::of_pid(child.pid()).unwrap(), ct.id());
+
+    child.exit(0).unwrap();
+    parent.exit(1).unwrap();
+
+    let evs = drain(&events);
+    assert_eq!(
+        types(&evs),
+        [CT_PR_EV_FORK, CT_PR_EV_EXIT, CT_PR_EV_EXIT, CT_PR_EV_EMPTY]
+    );
+    assert!(evs.iter().all(|ev| ev.ctid() == ct.id()));
+    assert!(evs[..3].iter().all(|ev| ev.flags().contains(CteFlags::INFO)));
+    assert!(!evs[3].flags().contains(CteFlags::INFO));
+    assert!(evs.windows(2).all(|w| w[0].evid() < w[1].evid()));
+
+    let st = ct.status(Detail::All).unwrap();
+    assert!(st.members().unwrap().is_empty());
+    assert_eq!(st.nevents(), 1);
+    ct.abandon().unwrap();
+}
+
+#[test]
+fn abandon_noorphan() {
+    let config = ProcessTemplateBuilder::new()
+        .informative(PrEventSet::EXIT | PrEventSet::EMPTY)
+        .params(PrParamSet::NOORPHAN);
+
+    let parent = spawn(&config);
+    let child = parent.fork().unwrap();
+    let (ppid, cpid) = (parent.pid(), child.pid());
+    let ct = Contract::open(parent.ctid()).unwrap();
+    let events = ct.events().unwrap();
+
+    ct.abandon().unwrap();
+
+    /*
+     * Abandoning the contract killed both members, which emptied it, and
+     * the contract is then gone.
+     */
+    let evs = drain(&events);
+    assert_eq!(types(&evs), [CT_PR_EV_EXIT, CT_PR_EV_EXIT, CT_PR_EV_EMPTY]);
+    assert!(matches!(child.exit(0), Err(ContractError::NoSuchContract)));
+    assert!(matches!(parent.exit(0), Err(ContractError::NoSuchContract)));
+    assert!(ContractId::of_pid(ppid).is_err());
+    assert!(ContractId::of_pid(cpid).is_err());
+    assert!(Status::read_ctid(ct.id(), Detail::Common).is_err());
+}
+
+#[test]
+fn ack() {
+    let config = ProcessTemplateBuilder::new()
+        .critical(PrEventSet::EMPTY)
+        .informative(PrEventSet::EXIT);
+    let bundle = EventEndpoint::bundle(ct_typeid_t::CTT_PROCESS).unwrap();
+
+    let p = spawn(&config);
+    let ct = Contract::open(p.ctid()).unwrap();
+    let events = ct.events().unwrap();
+    let later = ct.events().unwrap();
+    p.exit(0).unwrap();
+
+    let evs = drain(&events);
+    let [exit, empty] = evs.as_slice() else {
+        panic!("unexpected events: {:?}", types(&evs));
+    };
+    assert!(matches!(exit.ack(&ct), Err(ContractError::ResponseNotAllowed)));
+
+    /*
+     * Other tests post to the bundle too, so only our event is looked at.
+     */
+    let from_bundle = drain(&bundle)
+        .into_iter()
+        .find(|ev| ev.evid() == empty.evid())
+        .unwrap();
+    assert_eq!(from_bundle.source(), EndpointKind::Bundle);
+    assert!(matches!(
+        from_bundle.ack(&ct),
+        Err(ContractError::ResponseNotAllowed)
+    ));
+
+    let other = Contract::open(config.create().unwrap()).unwrap();
+    assert!(matches!(empty.ack(&other), Err(ContractError::InvalidArgument)));
+    other.abandon().unwrap();
+
+    assert_eq!(ct.status(Detail::Common).unwrap().nevents(), 1);
+    empty.ack(&ct).unwrap();
+    assert_eq!(ct.status(Detail::Common).unwrap().nevents(), 0);
+
+    /*
+     * The event is no longer pending, so a reset does not bring it back,
+     * and an endpoint that reads it only now sees that it was acknowledged.
+     */
+    events.reset().unwrap();
+    assert!(drain(&events).is_empty());
+    let evs = drain(&later);
+    assert!(evs[1].flags().contains(CteFlags::ACK));
+    assert!(matches!(evs[1].ack(&ct), Err(ContractError::ResponseNotAllowed)));
+    assert!(matches!(ct.ack(empty.evid()), Err(ContractError::NoSuchContract)));
+    ct.abandon().unwrap();
+}
+
+/*
+ * Device contracts are not simulated, so negotiations are driven by hand-made
+ * device events against a process contract, whose pending critical event
+ * stands in for the one proposing the transition.
+ */
+fn proposal() -> (Contract, DeviceEvent) {
+    let config = ProcessTemplateBuilder::new().critical(PrEventSet::EMPTY);
+    let p = spawn(&config);
+    let ct = Contract::open(p.ctid()).unwrap();
+    let events = ct.events().unwrap();
+    p.exit(0).unwrap();
+    let evid = drain(&events)[0].evid();
+
+    let ev = DeviceEvent {
+        ctid: ct.id(),
+        evid,
+        flags: CteFlags::NEG,
+        kind: DeviceEventKind::Transition(DeviceState::Offline),
+        minor: None,
+        noneg: None,
+    };
+    (ct, ev)
+}
+
+fn negend(
+    proposal: &DeviceEvent,
+    nevid: EventId,
+    newct: ContractId,
+) -> DeviceEvent {
+    DeviceEvent {
+        evid: EventId(proposal.evid.as_raw() + 1),
+        flags: CteFlags::INFO,
+        kind: DeviceEventKind::NegEnd { nevid, newct },
+        ..proposal.clone()
+    }
+}
+
+#[test]
+fn negotiation() {
+    let (ct, proposal) = proposal();
+    let evid = proposal.evid;
+
+    let not_neg = DeviceEvent { flags: CteFlags::empty(), ..proposal.clone() };
+    let other_ct = DeviceEvent { ctid: ContractId(0), ..proposal.clone() };
+    let end = negend(&proposal, evid, ct.id());
+    for ev in [&not_neg, &other_ct, &end] {
+        assert!(matches!(
+            Negotiation::start(&ct, ev),
+            Err(ContractError::InvalidArgument)
+        ));
+    }
+
+    let mut neg = Negotiation::start(&ct, &proposal).unwrap();
+    assert_eq!(neg.evid(), evid);
+    assert_eq!(neg.proposed(), DeviceState::Offline);
+    assert_eq!(neg.state(), NegotiationState::Pending);
+
+    neg.ack().unwrap();
+    assert_eq!(neg.state(), NegotiationState::Acked);
+    assert_eq!(ct.status(Detail::Common).unwrap().nevents(), 0);
+    assert!(matches!(neg.ack(), Err(ContractError::InvalidArgument)));
+    assert!(matches!(neg.nack(), Err(ContractError::InvalidArgument)));
+    assert!(matches!(neg.qack(), Err(ContractError::InvalidArgument)));
+
+    /*
+     * Only the end of this negotiation ends it, and only once.
+     */
+    assert!(!neg.handle(&proposal));
+    assert!(!neg.handle(&negend(
+        &proposal,
+        EventId(evid.as_raw() + 1),
+        ct.id()
+    )));
+    assert!(!neg.is_ended());
+    assert!(neg.handle(&end));
+    assert!(neg.is_ended());
+    assert_eq!(neg.state(), NegotiationState::Ended { newct: ct.id() });
+    assert!(!neg.handle(&end));
+    assert!(matches!(neg.nack(), Err(ContractError::InvalidArgument)));
+
+    ct.abandon().unwrap();
+}
+
+#[test]
+fn negotiation_replacement() {
+    let (ct, proposal) = proposal();
+    let evid = proposal.evid;
+
+    let mut neg = Negotiation::start(&ct, &proposal).unwrap();
+    assert!(neg.replacement().unwrap().is_none());
+
+    /*
+     * The mock cannot create device contracts, so it cannot build the
+     * replacement template either; the negotiation is left awaiting a
+     * response.
+     */
+    let config = DeviceTemplateBuilder::new()
+        .minor("/devices/pseudo/mock@0:mock")
+        .aset(DeviceStateSet::ALL);
+    assert!(matches!(
+        neg.ack_replacing(&config),
+        Err(ContractError::NotSupported)
+    ));
+    assert_eq!(neg.state(), NegotiationState::Pending);
+
+    /*
+     * A negotiation that ended without a new contract has no replacement,
+     * while one that ended with one opens it.
+     */
+    neg.ack().unwrap();
+    assert!(neg.handle(&negend(&proposal, evid, ct.id())));
+    assert!(neg.replacement().unwrap().is_none());
+
+    let newct = ProcessTemplateBuilder::new().create().unwrap();
+    let mut neg = Negotiation::start(&ct, &proposal).unwrap();
+    assert!(neg.handle(&negend(&proposal, evid, newct)));
+    assert_eq!(neg.replacement().unwrap().unwrap().id(), newct);
+
+    Contract::open(newct).unwrap().abandon().unwrap();
+    ct.abandon().unwrap();
+}
+
+/*
+ * Returns those of `ids` that are in `listed`, in order.
+ */
+fn among(listed: AllContracts, ids: &[ContractId]) -> Vec<ContractId> {
+    let mut found: Vec<_> = listed
+        .map(|res| res.unwrap().0)
+        .filter(|id| ids.contains(id))
+        .collect();
+    found.sort();
+    found
+}
+
+#[test]
+fn enumerate() {
+    let config = ProcessTemplateBuilder::new();
+
+    let held = config.create().unwrap();
+    let gone = config.create().unwrap();
+    Contract::open(gone).unwrap().abandon().unwrap();
+    let p = spawn(&config);
+    let orphan = p.ctid();
+    Contract::open(orphan).unwrap().abandon().unwrap();
+    let ours = [held, gone, orphan];
+
+    assert_eq!(among(all_contracts().unwrap(), &ours), [held, orphan]);
+    let owned = all_contracts().unwrap().in_state(ctstate_t::CTS_OWNED);
+    assert_eq!(among(owned, &ours), [held]);
+    let process = all_contracts().unwrap().of_type(ct_typeid_t::CTT_PROCESS);
+    assert_eq!(among(process, &ours), [held, orphan]);
+    let device = all_contracts().unwrap().of_type(ct_typeid_t::CTT_DEVICE);
+    assert!(among(device, &ours).is_empty());
+
+    let (id, snap) = all_contracts()
+        .unwrap()
+        .detail(Detail::All)
+        .map(Result::unwrap)
+        .find(|(id, _)| *id == held)
+        .unwrap();
+    assert_eq!(id, snap.id);
+    assert_eq!(snap.detail, Detail::All);
+    assert_eq!(snap.state, ctstate_t::CTS_OWNED);
+    assert_eq!(snap.process.unwrap().members, Some(vec![]));
+
+    p.exit(0).unwrap();
+    Contract::open(held).unwrap().abandon().unwrap();
+}
+
+#[test]
+fn orphan_adoption() {
+    let config = ProcessTemplateBuilder::new().informative(PrEventSet::EXIT);
+
+    let held = config.create().unwrap();
+    let p = spawn(&config);
+    let orphan = p.ctid();
+    Contract::open(orphan).unwrap().abandon().unwrap();
+    let ours = [held, orphan];
+
+    assert_eq!(among(orphans().unwrap(), &ours), [orphan]);
+    assert!(matches!(Contract::adopt_id(held), Err(ContractError::Busy)));
+
+    /*
+     * Once adopted, the contract is no longer an orphan, and its events are
+     * ours to read again.
+     */
+    let ct = Contract::adopt_id(orphan).unwrap();
+    assert_eq!(
+        ct.status(Detail::Common).unwrap().state(),
+        ctstate_t::CTS_OWNED
+    );
+    assert!(among(orphans().unwrap(), &ours).is_empty());
+    assert!(matches!(ct.adopt(), Err(ContractError::Busy)));
+
+    let events = ct.events().unwrap();
+    p.exit(0).unwrap();
+    assert_eq!(types(&drain(&events)), [CT_PR_EV_EXIT, CT_PR_EV_EMPTY]);
+
+    ct.abandon().unwrap();
+    Contract::open(held).unwrap().abandon().unwrap();
+}
+
+#[test]
+fn recovery() {
+    let config = ProcessTemplateBuilder::new().informative(PrEventSet::EXIT);
+
+    let orphaned = spawn(&config);
+    Contract::open(orphaned.ctid()).unwrap().abandon().unwrap();
+    let held = config.create().unwrap();
+    let other = spawn(&config);
+    let busy = spawn(&config);
+    Contract::open(busy.ctid()).unwrap().abandon().unwrap();
+    other.adopt(busy.ctid()).unwrap();
+    let dead = config.create().unwrap();
+    Contract::open(dead).unwrap().abandon().unwrap();
+
+    let mgr: AdoptionManager =
+        [orphaned.ctid(), held, busy.ctid(), dead, held].into_iter().collect();
+    assert_eq!(mgr.ctids(), [orphaned.ctid(), held, busy.ctid(), dead]);
+
+    let Recovery { recovered, failed } = mgr.recover();
+    let recovered: Vec<_> =
+        recovered.iter().map(|r| (r.contract.id(), r.adopted)).collect();
+    assert_eq!(recovered, [(orphaned.ctid(), true), (held, false)]);
+    assert!(matches!(
+        failed.as_slice(),
+        [(b, ContractError::Busy), (d, ContractError::NoSuchContract)]
+            if *b == busy.ctid() && *d == dead
+    ));
+
+    /*
+     * Recovering again finds the adopted contract held by the caller.
+     */
+    let again = AdoptionManager::new().add(orphaned.ctid()).recover();
+    assert!(!again.recovered[0].adopted);
+
+    let events = &again.recovered[0].events;
+    orphaned.exit(0).unwrap();
+    assert_eq!(types(&drain(events)), [CT_PR_EV_EXIT, CT_PR_EV_EMPTY]);
+
+    busy.exit(0).unwrap();
+    other.exit(0).unwrap();
+    again.recovered[0].contract.abandon().unwrap();
+    Contract::open(held).unwrap().abandon().unwrap();
+}
+
+#[test]
+fn export() {
+    let config = ProcessTemplateBuilder::new()
+        .critical(PrEventSet::EMPTY)
+        .informative(PrEventSet::EXIT);
+    let p = spawn(&config);
+    let (ctid, pid) = (p.ctid(), p.pid());
+    let ct = Contract::open(ctid).unwrap();
+    let events = ct.events().unwrap();
+    p.exit(2).unwrap();
+
+    let evs = drain(&events);
+    let [exit, empty] = evs.as_slice() else {
+        panic!("unexpected events: {:?}", types(&evs));
+    };
+    assert_eq!(
+        exit.to_string(),
+        format!(
+            "{ctid} {} info - - contract_type=\"process\" type=\"exit\" \
+             pid={pid} exit_status=512",
+            exit.evid()
+        )
+    );
+    assert_eq!(
+        empty.to_json(),
+        format!(
+            "{{\"ctid\":{ctid},\"evid\":{},\"critical\":true,\
+             \"acked\":false,\"negotiation\":false,\
+             \"contract_type\":\"process\",\"type\":\"empty\",\
+             \"pid\":{pid}}}",
+            empty.evid()
+        )
+    );
+    ct.abandon().unwrap();
+}
+
+#[test]
+fn report() {
+    let config = ProcessTemplateBuilder::new()
+        .informative(PrEventSet::EXIT)
+        .critical(PrEventSet::EMPTY);
+
+    let held = config.create().unwrap();
+    let p = spawn(&config);
+    let orphan = p.ctid();
+    Contract::open(orphan).unwrap().abandon().unwrap();
+
+    let reports: Vec<ContractReport> = report_all()
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|r| r.id == held || r.id == orphan)
+        .collect();
+    let report = |ctid| reports.iter().find(|r| r.id == ctid).unwrap();
+    let (held_report, orphan_report) = (report(held), report(orphan));
+    assert_eq!(reports.len(), 2);
+
+    assert_eq!(held_report.state, "owned");
+    assert_eq!(orphan_report.state, "orphan");
+    for r in [held_report, orphan_report] {
+        assert_eq!(r.type_name, "process");
+        assert_eq!(r.informative, ["exit"]);
+        assert_eq!(r.critical, ["empty"]);
+        assert!(r.device.is_none());
+        assert_eq!(&ContractReport::for_contract(r.id).unwrap(), r);
+    }
+    assert_eq!(orphan_report.process.as_ref().unwrap().members, [p.pid()]);
+
+    p.exit(0).unwrap();
+    Contract::open(held).unwrap().abandon().unwrap();
+}
+
+#[test]
+fn bundle_in_zone() {
+    let bundle = |zoneid| {
+        EventEndpoint::bundle(ct_typeid_t::CTT_PROCESS).unwrap().in_zone(zoneid)
+    };
+    let global = bundle(GLOBAL_ZONEID);
+    let other = bundle(ZoneId(3));
+    let of = |evs: Vec<Event>, ctid| {
+        types(
+            &evs.into_iter().filter(|ev| ev.ctid() == ctid).collect::<Vec<_>>(),
+        )
+    };
+
+    let config = ProcessTemplateBuilder::new()
+        .informative(PrEventSet::EXIT)
+        .critical(PrEventSet::EMPTY);
+    let held = spawn(&config);
+    let orphan = spawn(&config);
+    let (held_ct, orphan_ct) = (held.ctid(), orphan.ctid());
+    Contract::open(orphan_ct).unwrap().abandon().unwrap();
+
+    /*
+     * The held contract outlives its events, so its zone is known and they
+     * are filtered by it.
+     */
+    held.exit(0).unwrap();
+    assert_eq!(of(drain(&global), held_ct), [CT_PR_EV_EXIT, CT_PR_EV_EMPTY]);
+    assert!(of(drain(&other), held_ct).is_empty());
+
+    /*
+     * The orphan goes away as it empties, before its events are read, and
+     * they are let through rather than lost.
+     */
+    orphan.exit(0).unwrap();
+    assert_eq!(of(drain(&global), orphan_ct), [CT_PR_EV_EXIT, CT_PR_EV_EMPTY]);
+
+    Contract::open(held_ct).unwrap().abandon().unwrap();
+}
+
+/*
+ * Both cases are checked in one test, as the environment is shared by every
+ * test in this file.
+ */
+#[test]
+fn smf_method() {
+    std::env::remove_var("SMF_FMRI");
+    std::env::set_var("SMF_METHOD", "start");
+    let config =
+        ProcessTemplateBuilder::new().critical(PrEventSet::EXIT).smf_method();
+    let ctid = config.create().unwrap();
+    let st = Status::read_ctid(ctid, Detail::All).unwrap();
+    assert_eq!(st.critical(), CT_PR_EV_EXIT);
+    assert!(st.svc_fmri().unwrap().is_empty());
+    assert!(st.svc_aux().unwrap().is_empty());
+    Contract::open(ctid).unwrap().abandon().unwrap();
+
+    std::env::set_var("SMF_FMRI", "svc:/site/example:default");
+    let config = ProcessTemplateBuilder::new()
+        .informative(PrEventSet::EXIT)
+        .cookie(7)
+        .smf_method();
+    std::env::remove_var("SMF_FMRI");
+    std::env::remove_var("SMF_METHOD");
+    let ctid = config.create().unwrap();
+    let st = Status::read_ctid(ctid, Detail::All).unwrap();
+    assert_eq!(
+        PrEventSet::from_bits_retain(st.critical()),
+        PrEventSet::EMPTY
+            | PrEventSet::CORE
+            | PrEventSet::SIGNAL
+            | PrEventSet::HWERR
+    );
+    assert_eq!(st.informative(), 0);
+    assert_eq!(st.cookie(), 7);
+    assert_eq!(
+        st.svc_fmri().unwrap().to_str().unwrap(),
+        "svc:/site/example:default"
+    );
+    assert_eq!(st.svc_aux().unwrap().to_str().unwrap(), "start");
+
+    let pr = StatusSnapshot::read(ctid, Detail::All).unwrap().process.unwrap();
+    assert_eq!(pr.fatal, PrEventSet::HWERR);
+    assert_eq!(pr.params, PrParamSet::INHERIT | PrParamSet::REGENT);
+    Contract::open(ctid).unwrap().abandon().unwrap();
+}
+
+#[test]
+fn transfer_subject() {
+    let config = ProcessTemplateBuilder::new().informative(PrEventSet::EXIT);
+    let tmpl = config.build().unwrap();
+
+    let empty = config.create().unwrap();
+    tmpl.set_transfer(empty).unwrap();
+    assert_eq!(tmpl.transfer().unwrap(), empty);
+
+    let busy = spawn(&config);
+    assert!(matches!(
+        tmpl.set_transfer(busy.ctid()),
+        Err(ContractError::NotEmpty)
+    ));
+
+    let orphan = spawn(&config);
+    Contract::open(orphan.ctid()).unwrap().abandon().unwrap();
+    assert!(matches!(
+        tmpl.set_transfer(orphan.ctid()),
+        Err(ContractError::NotHeld)
+    ));
+
+    /*
+     * Once another process adopts the orphan, it is held, but not by us.
+     */
+    busy.adopt(orphan.ctid()).unwrap();
+    assert!(matches!(
+        tmpl.set_transfer(orphan.ctid()),
+        Err(ContractError::NotHeld)
+    ));
+
+    assert!(matches!(
+        tmpl.set_transfer(ContractId(i32::MAX)),
+        Err(ContractError::NoSuchContract)
+    ));
+    assert_eq!(tmpl.transfer().unwrap(), empty);
+
+    let held = busy.ctid();
+    orphan.exit(0).unwrap();
+    busy.exit(0).unwrap();
+    Contract::open(held).unwrap().abandon().unwrap();
+    Contract::open(empty).unwrap().abandon().unwrap();
+}